@@ -87,11 +87,14 @@ impl Plugin for SuperconductorPlugin {
         app.world
             .spawn_empty()
             .insert(components::InstanceOf(model))
-            .insert(components::Instance(renderer_core::Instance::new(
-                Vec3::new(0.0, 0.0, 0.0),
-                1.0,
-                Default::default(),
-            )));
+            .insert(components::Instance(
+                renderer_core::gltf_helpers::Similarity::new(
+                    Vec3::new(0.0, 0.0, 0.0),
+                    1.0,
+                    Default::default(),
+                )
+                .into(),
+            ));
 
         let camera_rig: dolly::rig::CameraRig = dolly::rig::CameraRig::builder()
             .with(dolly::drivers::Position::new(Vec3::new(0.0, 1.75, 0.0)))
@@ -300,7 +303,12 @@ struct KeyboardState {
 
 fn rotate_entities(mut query: Query<&mut components::Instance, With<Spinning>>) {
     query.for_each_mut(|mut instance| {
-        instance.0.rotation *= renderer_core::glam::Quat::from_rotation_y(0.01)
+        instance.0 = instance.0
+            * renderer_core::Instance::from(renderer_core::gltf_helpers::Similarity::new(
+                Vec3::ZERO,
+                1.0,
+                renderer_core::glam::Quat::from_rotation_y(0.01),
+            ));
     });
 }
 
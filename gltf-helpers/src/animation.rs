@@ -1,44 +1,76 @@
-use crate::{DepthFirstNodes, Extensions, Similarity};
+use crate::{DepthFirstNodes, Extensions, Transform};
 use glam::{Mat4, Quat, Vec3};
 use goth_gltf::{Interpolation, TargetPath};
 use std::borrow::Cow;
 use std::fmt;
-use std::ops::{Add, Mul};
+use std::ops::{Add, Mul, Range};
 
 pub fn read_animations<'a, F1, F3, F4>(
     gltf: &'a goth_gltf::Gltf<Extensions>,
     read_f32: F1,
     read_f32x3: F3,
     read_f32x4: F4,
-) -> Vec<Animation>
+) -> anyhow::Result<Vec<Animation>>
 where
-    F1: Fn(&goth_gltf::Accessor) -> Cow<'a, [f32]>,
-    F3: Fn(&goth_gltf::Accessor) -> Cow<'a, [[f32; 3]]>,
-    F4: Fn(&goth_gltf::Accessor) -> Cow<'a, [[f32; 4]]>,
+    F1: Fn(&goth_gltf::Accessor) -> anyhow::Result<Cow<'a, [f32]>>,
+    F3: Fn(&goth_gltf::Accessor) -> anyhow::Result<Cow<'a, [[f32; 3]]>>,
+    F4: Fn(&goth_gltf::Accessor) -> anyhow::Result<Cow<'a, [[f32; 4]]>>,
 {
+    let accessor = |index: usize| {
+        gltf.accessors.get(index).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Accessor index {} is out of range of {}",
+                index,
+                gltf.accessors.len()
+            )
+        })
+    };
+
     gltf.animations
         .iter()
-        .map(|animation| {
+        .enumerate()
+        .map(|(animation_index, animation)| {
             let mut translation_channels: Vec<Channel<Vec3>> = Vec::new();
             let mut rotation_channels: Vec<Channel<Quat>> = Vec::new();
-            let mut scale_channels: Vec<Channel<f32>> = Vec::new();
+            let mut scale_channels: Vec<Channel<Vec3>> = Vec::new();
+            let mut weight_channels: Vec<(usize, Channel<f32>)> = Vec::new();
 
             for channel in &animation.channels {
-                let sampler = &animation.samplers[channel.sampler];
-
-                let input_accessor = &gltf.accessors[sampler.input];
-
-                let output_accessor = &gltf.accessors[sampler.output];
-
-                let inputs = read_f32(input_accessor).to_vec();
+                let sampler = animation.samplers.get(channel.sampler).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Animation {} references missing sampler {}",
+                        animation_index,
+                        channel.sampler
+                    )
+                })?;
+
+                // Channels without a node are meant to be targeted by extensions, which we don't
+                // support.
+                let node_index = match channel.target.node {
+                    Some(node_index) => node_index,
+                    None => continue,
+                };
+
+                let input_accessor = accessor(sampler.input)?;
+
+                let output_accessor = accessor(sampler.output)?;
+
+                let inputs = read_f32(input_accessor)?.to_vec();
+
+                if inputs.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "Animation {} has a sampler without any keyframes",
+                        animation_index
+                    ));
+                }
 
                 match channel.target.path {
                     TargetPath::Translation => {
                         translation_channels.push(Channel {
                             interpolation: sampler.interpolation,
                             inputs,
-                            node_index: channel.target.node.unwrap(),
-                            outputs: read_f32x3(output_accessor)
+                            node_index,
+                            outputs: read_f32x3(output_accessor)?
                                 .iter()
                                 .copied()
                                 .map(Vec3::from)
@@ -49,9 +81,9 @@ where
                         rotation_channels.push(Channel {
                             interpolation: sampler.interpolation,
                             inputs,
-                            node_index: channel.target.node.unwrap(),
+                            node_index,
                             outputs: {
-                                read_f32x4(output_accessor)
+                                read_f32x4(output_accessor)?
                                     .iter()
                                     .copied()
                                     .map(Quat::from_array)
@@ -63,15 +95,42 @@ where
                         scale_channels.push(Channel {
                             interpolation: sampler.interpolation,
                             inputs,
-                            node_index: channel.target.node.unwrap(),
-                            outputs: read_f32x3(output_accessor)
+                            node_index,
+                            outputs: read_f32x3(output_accessor)?
                                 .iter()
-                                .map(|scale| scale[0].max(scale[1]).max(scale[2]))
+                                .copied()
+                                .map(Vec3::from)
                                 .collect(),
                         });
                     }
                     TargetPath::Weights => {
-                        log::warn!("Weight animations are not supported, ignoring.");
+                        let outputs = read_f32(output_accessor)?;
+
+                        let values_per_keyframe = match sampler.interpolation {
+                            Interpolation::CubicSpline => 3,
+                            _ => 1,
+                        };
+
+                        let num_weights = outputs.len() / (inputs.len() * values_per_keyframe);
+
+                        // Split the outputs into a channel per weight, as each keyframe contains
+                        // a value for each of the mesh's morph targets.
+                        for weight_index in 0..num_weights {
+                            weight_channels.push((
+                                weight_index,
+                                Channel {
+                                    interpolation: sampler.interpolation,
+                                    inputs: inputs.clone(),
+                                    node_index,
+                                    outputs: outputs
+                                        .iter()
+                                        .copied()
+                                        .skip(weight_index)
+                                        .step_by(num_weights)
+                                        .collect(),
+                                },
+                            ));
+                        }
                     }
                 }
             }
@@ -89,45 +148,83 @@ where
                         .iter()
                         .map(|channel| channel.inputs[channel.inputs.len() - 1]),
                 )
+                .chain(
+                    weight_channels
+                        .iter()
+                        .map(|(_, channel)| channel.inputs[channel.inputs.len() - 1]),
+                )
                 .max_by_key(|&time| ordered_float::OrderedFloat(time))
-                .unwrap();
+                // Animations without any channels don't do anything.
+                .unwrap_or(0.0);
 
-            Animation {
+            Ok(Animation {
                 total_time,
                 translation_channels,
                 rotation_channels,
                 scale_channels,
-            }
+                weight_channels,
+            })
         })
         .collect()
 }
 
+// The local transform of a node, kept as separate components so that animation channels can
+// set them individually.
+#[derive(Clone, Copy, Debug)]
+pub struct LocalTransform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl LocalTransform {
+    fn as_transform(&self) -> Transform {
+        Transform::new_from_gltf(
+            self.translation.into(),
+            self.rotation.into(),
+            self.scale.into(),
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct AnimationJoints {
-    global_transforms: Vec<Similarity>,
-    local_transforms: Vec<Similarity>,
+    global_transforms: Vec<Transform>,
+    local_transforms: Vec<LocalTransform>,
 }
 
 impl AnimationJoints {
     pub fn new(gltf: &goth_gltf::Gltf<Extensions>, depth_first_nodes: &DepthFirstNodes) -> Self {
-        let node_transforms: Vec<_> = gltf
+        let local_transforms: Vec<_> = gltf
             .nodes
             .iter()
             .map(|node| match node.transform() {
+                // Animated nodes can't use matrices, so it's fine to drop any shear here.
                 goth_gltf::NodeTransform::Matrix(matrix) => {
-                    Similarity::new_from_mat4(Mat4::from_cols_array(&matrix))
+                    let (scale, rotation, translation) =
+                        Mat4::from_cols_array(&matrix).to_scale_rotation_translation();
+
+                    LocalTransform {
+                        translation,
+                        rotation,
+                        scale,
+                    }
                 }
                 goth_gltf::NodeTransform::Set {
                     translation,
                     rotation,
                     scale,
-                } => Similarity::new_from_gltf(translation, rotation, scale),
+                } => LocalTransform {
+                    translation: translation.into(),
+                    rotation: Quat::from_array(rotation),
+                    scale: scale.into(),
+                },
             })
             .collect();
 
         let mut joints = Self {
-            global_transforms: node_transforms.clone(),
-            local_transforms: node_transforms,
+            global_transforms: vec![Transform::IDENTITY; local_transforms.len()],
+            local_transforms,
         };
 
         joints.update(depth_first_nodes);
@@ -138,9 +235,9 @@ impl AnimationJoints {
     pub fn iter<'a>(
         &'a mut self,
         joint_indices_to_node_indices: &'a [usize],
-        inverse_bind_transforms: &'a [Similarity],
+        inverse_bind_transforms: &'a [Transform],
         depth_first_nodes: &DepthFirstNodes,
-    ) -> impl Iterator<Item = Similarity> + 'a {
+    ) -> impl Iterator<Item = Transform> + 'a {
         self.update(depth_first_nodes);
 
         joint_indices_to_node_indices
@@ -153,13 +250,13 @@ impl AnimationJoints {
 
     pub fn update(&mut self, depth_first_nodes: &DepthFirstNodes) {
         for &index in &depth_first_nodes.roots {
-            self.global_transforms[index] = self.local_transforms[index];
+            self.global_transforms[index] = self.local_transforms[index].as_transform();
         }
 
         for child in &depth_first_nodes.children {
             let parent_transform = self.global_transforms[child.parent];
             self.global_transforms[child.index] =
-                parent_transform * self.local_transforms[child.index];
+                parent_transform * self.local_transforms[child.index].as_transform();
         }
     }
 
@@ -169,8 +266,8 @@ impl AnimationJoints {
     ) -> impl Iterator<Item = (Vec3, Vec3)> + 'a {
         depth_first_nodes.children.iter().map(|child| {
             (
-                self.global_transforms[child.parent].translation,
-                self.global_transforms[child.index].translation,
+                self.global_transforms[child.parent].translation(),
+                self.global_transforms[child.index].translation(),
             )
         })
     }
@@ -179,11 +276,77 @@ impl AnimationJoints {
         &mut self,
         index: usize,
         joint_indices_to_node_indices: &[usize],
-    ) -> &mut Similarity {
+    ) -> &mut LocalTransform {
         &mut self.local_transforms[joint_indices_to_node_indices[index]]
     }
 }
 
+// The morph target weights of each node's mesh, packed into a single array.
+#[derive(Clone, Debug)]
+pub struct MorphWeights {
+    weights: Vec<f32>,
+    node_ranges: Vec<Range<usize>>,
+}
+
+impl MorphWeights {
+    pub fn new(gltf: &goth_gltf::Gltf<Extensions>) -> Self {
+        let mut weights = Vec::new();
+
+        let node_ranges = gltf
+            .nodes
+            .iter()
+            .map(|node| {
+                let start = weights.len();
+
+                if let Some(mesh) = node.mesh.and_then(|index| gltf.meshes.get(index)) {
+                    let num_targets = mesh
+                        .primitives
+                        .first()
+                        .map(|primitive| primitive.targets.len())
+                        .unwrap_or(0);
+
+                    weights.extend(
+                        (0..num_targets)
+                            .map(|index| mesh.weights.get(index).copied().unwrap_or(0.0)),
+                    );
+                }
+
+                start..weights.len()
+            })
+            .collect();
+
+        Self {
+            weights,
+            node_ranges,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[f32] {
+        &self.weights
+    }
+
+    pub fn len(&self) -> usize {
+        self.weights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weights.is_empty()
+    }
+
+    // The offset of a node's weights into the packed array.
+    pub fn node_offset(&self, node_index: usize) -> usize {
+        self.node_ranges[node_index].start
+    }
+
+    pub fn node_weights(&self, node_index: usize) -> &[f32] {
+        &self.weights[self.node_ranges[node_index].clone()]
+    }
+
+    pub fn node_weights_mut(&mut self, node_index: usize) -> &mut [f32] {
+        &mut self.weights[self.node_ranges[node_index].clone()]
+    }
+}
+
 struct Channel<T> {
     interpolation: Interpolation,
     inputs: Vec<f32>,
@@ -269,7 +432,8 @@ pub struct Animation {
     total_time: f32,
     translation_channels: Vec<Channel<Vec3>>,
     rotation_channels: Vec<Channel<Quat>>,
-    scale_channels: Vec<Channel<f32>>,
+    scale_channels: Vec<Channel<Vec3>>,
+    weight_channels: Vec<(usize, Channel<f32>)>,
 }
 
 impl Animation {
@@ -277,7 +441,19 @@ impl Animation {
         self.total_time
     }
 
-    pub fn animate(&self, animation_joints: &mut AnimationJoints, time: f32) {
+    pub fn has_non_uniform_scales(&self) -> bool {
+        self.scale_channels
+            .iter()
+            .flat_map(|channel| &channel.outputs)
+            .any(|&scale| !crate::is_uniform_scale(scale.into()))
+    }
+
+    pub fn animate(
+        &self,
+        animation_joints: &mut AnimationJoints,
+        morph_weights: &mut MorphWeights,
+        time: f32,
+    ) {
         self.translation_channels
             .iter()
             .filter_map(move |channel| channel.sample(time))
@@ -298,6 +474,17 @@ impl Animation {
             .for_each(|(node_index, scale)| {
                 animation_joints.local_transforms[node_index].scale = scale;
             });
+
+        for (weight_index, channel) in &self.weight_channels {
+            if let Some((node_index, weight)) = channel.sample(time) {
+                if let Some(node_weight) = morph_weights
+                    .node_weights_mut(node_index)
+                    .get_mut(*weight_index)
+                {
+                    *node_weight = weight;
+                }
+            }
+        }
     }
 }
 
@@ -1,5 +1,5 @@
 pub mod animation;
-use glam::{Mat3, Mat4, Quat, Vec3};
+use glam::{Affine3A, Mat3, Mat4, Quat, Vec3};
 use std::ops::Mul;
 
 pub type Extensions = goth_gltf::default_extensions::Extensions;
@@ -42,13 +42,10 @@ impl Similarity {
     }
 
     pub fn new_from_gltf(translation: [f32; 3], rotation: [f32; 4], scale: [f32; 3]) -> Self {
-        let scale_x_y_approx_eq = (scale[0] - scale[1]).abs() <= std::f32::EPSILON * 10.0;
-        let scale_x_z_approx_eq = (scale[0] - scale[2]).abs() <= std::f32::EPSILON * 10.0;
-
         let max_scale = scale[0].max(scale[1]).max(scale[2]);
 
-        if !(scale_x_y_approx_eq && scale_x_z_approx_eq) {
-            log::warn!("Node scales are not uniform: {:?}. Using the largest scale of {:?}. This may result in the model looking odd.", scale, max_scale);
+        if !is_uniform_scale(scale) {
+            log::warn!("Node scales are not uniform: {:?}. Using the largest scale of {:?}. Use `Transform` to keep the non-uniform scale.", scale, max_scale);
         }
 
         Similarity {
@@ -103,16 +100,157 @@ impl Mul<Vec3> for Similarity {
     }
 }
 
+fn is_uniform_scale(scale: [f32; 3]) -> bool {
+    let scale_x_y_approx_eq = (scale[0] - scale[1]).abs() <= std::f32::EPSILON * 10.0;
+    let scale_x_z_approx_eq = (scale[0] - scale[2]).abs() <= std::f32::EPSILON * 10.0;
+
+    scale_x_y_approx_eq && scale_x_z_approx_eq
+}
+
+// A transform that uses a `Similarity` when the scale is uniform and falls back to a full affine
+// transform for non-uniform scales (and shears, when they come from a matrix).
+#[derive(Clone, Copy, Debug)]
+pub enum Transform {
+    Similarity(Similarity),
+    Affine(Affine3A),
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self::Similarity(Similarity::IDENTITY);
+
+    pub fn new_from_gltf(translation: [f32; 3], rotation: [f32; 4], scale: [f32; 3]) -> Self {
+        if is_uniform_scale(scale) {
+            Self::Similarity(Similarity::new(
+                translation.into(),
+                scale[0],
+                Quat::from_array(rotation),
+            ))
+        } else {
+            Self::Affine(Affine3A::from_scale_rotation_translation(
+                scale.into(),
+                Quat::from_array(rotation),
+                translation.into(),
+            ))
+        }
+    }
+
+    pub fn new_from_mat4(mat4: Mat4) -> Self {
+        let (scale, rotation, translation) = mat4.to_scale_rotation_translation();
+
+        if is_uniform_scale(scale.into()) {
+            Self::Similarity(Similarity::new(translation, scale.x, rotation))
+        } else {
+            Self::Affine(Affine3A::from_mat4(mat4))
+        }
+    }
+
+    pub fn new_from_gltf_node(node: &goth_gltf::Node<Extensions>) -> Self {
+        match node.transform() {
+            goth_gltf::NodeTransform::Matrix(matrix) => {
+                Self::new_from_mat4(Mat4::from_cols_array(&matrix))
+            }
+            goth_gltf::NodeTransform::Set {
+                translation,
+                rotation,
+                scale,
+            } => Self::new_from_gltf(translation, rotation, scale),
+        }
+    }
+
+    pub fn is_affine(&self) -> bool {
+        matches!(self, Self::Affine(_))
+    }
+
+    pub fn as_affine(self) -> Affine3A {
+        match self {
+            Self::Similarity(similarity) => Affine3A::from_scale_rotation_translation(
+                Vec3::splat(similarity.scale),
+                similarity.rotation,
+                similarity.translation,
+            ),
+            Self::Affine(affine) => affine,
+        }
+    }
+
+    pub fn as_mat4(self) -> Mat4 {
+        match self {
+            Self::Similarity(similarity) => similarity.as_mat4(),
+            Self::Affine(affine) => Mat4::from(affine),
+        }
+    }
+
+    pub fn translation(&self) -> Vec3 {
+        match self {
+            Self::Similarity(similarity) => similarity.translation,
+            Self::Affine(affine) => affine.translation.into(),
+        }
+    }
+
+    // The largest amount that the transform scales along any axis, for scaling bounding spheres.
+    pub fn max_scale(&self) -> f32 {
+        match self {
+            Self::Similarity(similarity) => similarity.scale,
+            Self::Affine(affine) => affine
+                .matrix3
+                .x_axis
+                .length()
+                .max(affine.matrix3.y_axis.length())
+                .max(affine.matrix3.z_axis.length()),
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        match self {
+            Self::Similarity(similarity) => Self::Similarity(similarity.inverse()),
+            Self::Affine(affine) => Self::Affine(affine.inverse()),
+        }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl From<Similarity> for Transform {
+    fn from(similarity: Similarity) -> Self {
+        Self::Similarity(similarity)
+    }
+}
+
+impl Mul<Transform> for Transform {
+    type Output = Self;
+
+    fn mul(self, child: Self) -> Self {
+        match (self, child) {
+            (Self::Similarity(parent), Self::Similarity(child)) => Self::Similarity(parent * child),
+            (parent, child) => Self::Affine(parent.as_affine() * child.as_affine()),
+        }
+    }
+}
+
+impl Mul<Vec3> for Transform {
+    type Output = Vec3;
+
+    fn mul(self, vector: Vec3) -> Vec3 {
+        match self {
+            Self::Similarity(similarity) => similarity * vector,
+            Self::Affine(affine) => affine.transform_point3(vector),
+        }
+    }
+}
+
 pub struct NodeTree {
-    inner: Vec<(Similarity, usize)>,
+    inner: Vec<(Transform, usize)>,
 }
 
 impl NodeTree {
     pub fn new(gltf: &goth_gltf::Gltf<Extensions>) -> Self {
-        let mut inner = vec![(Similarity::IDENTITY, usize::max_value()); gltf.nodes.len()];
+        let mut inner = vec![(Transform::IDENTITY, usize::max_value()); gltf.nodes.len()];
 
         for (index, node) in gltf.nodes.iter().enumerate() {
-            inner[index].0 = Similarity::new_from_gltf_node(node);
+            inner[index].0 = Transform::new_from_gltf_node(node);
             for child in &node.children {
                 inner[*child].1 = index;
             }
@@ -121,8 +259,8 @@ impl NodeTree {
         Self { inner }
     }
 
-    pub fn transform_of(&self, mut index: usize) -> Similarity {
-        let mut transform_sum = Similarity::IDENTITY;
+    pub fn transform_of(&self, mut index: usize) -> Transform {
+        let mut transform_sum = Transform::IDENTITY;
 
         while index != usize::max_value() {
             let (transform, parent_index) = self.inner[index];
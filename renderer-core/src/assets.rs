@@ -1,16 +1,99 @@
+pub mod http_clients;
+pub mod load_scheduler;
 pub mod models;
 pub mod textures;
 
 use std::ops::Range;
+use std::sync::Arc;
 
 #[cfg(not(feature = "wasm"))]
-pub type HttpClientFuture =
-    std::pin::Pin<Box<dyn core::future::Future<Output = anyhow::Result<Vec<u8>>> + Send>>;
+pub type HttpClientFuture<T = Vec<u8>> =
+    std::pin::Pin<Box<dyn core::future::Future<Output = anyhow::Result<T>> + Send>>;
 
 #[cfg(feature = "wasm")]
-pub type HttpClientFuture =
-    std::pin::Pin<Box<dyn core::future::Future<Output = anyhow::Result<Vec<u8>>>>>;
+pub type HttpClientFuture<T = Vec<u8>> =
+    std::pin::Pin<Box<dyn core::future::Future<Output = anyhow::Result<T>>>>;
+
+// The headers that a server can give for a response, which can be sent back
+// with later requests to check whether the response has changed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+// Reported while a response is being fetched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FetchProgress {
+    // The size of the response. This is the Content-Length header if the server sent one,
+    // otherwise it's reported once the whole response has arrived.
+    ContentLength(u64),
+    // The size of a chunk of the response that has arrived.
+    Chunk(u64),
+}
+
+pub type FetchProgressCallback = Arc<dyn Fn(FetchProgress) + Send + Sync>;
+
+// For clients that only get to see a response once all of it has arrived.
+pub fn report_whole_response(progress: &FetchProgressCallback, bytes: usize) {
+    progress(FetchProgress::ContentLength(bytes as u64));
+    progress(FetchProgress::Chunk(bytes as u64));
+}
+
+pub enum ConditionalResponse {
+    Modified {
+        bytes: Vec<u8>,
+        validators: Validators,
+    },
+    NotModified,
+}
 
 pub trait HttpClient: Clone + Send + Sync + 'static {
     fn fetch_bytes(&self, url: &url::Url, range: Option<Range<usize>>) -> HttpClientFuture;
+
+    // Like `fetch_bytes`, but reports the progress of the response as it arrives. Clients that
+    // don't stream responses report all of it at once.
+    fn fetch_bytes_with_progress(
+        &self,
+        url: &url::Url,
+        range: Option<Range<usize>>,
+        progress: FetchProgressCallback,
+    ) -> HttpClientFuture {
+        let future = self.fetch_bytes(url, range);
+
+        Box::pin(async move {
+            let bytes = future.await?;
+            report_whole_response(&progress, bytes.len());
+            Ok(bytes)
+        })
+    }
+
+    // Fetch the bytes unless they still match the validators of a previous response. Clients that
+    // don't support conditional requests just fetch the bytes again. Progress is only reported for
+    // bytes that are fetched.
+    fn fetch_bytes_if_modified(
+        &self,
+        url: &url::Url,
+        range: Option<Range<usize>>,
+        _validators: &Validators,
+        progress: Option<FetchProgressCallback>,
+    ) -> HttpClientFuture<ConditionalResponse> {
+        let future = match progress {
+            Some(progress) => self.fetch_bytes_with_progress(url, range, progress),
+            None => self.fetch_bytes(url, range),
+        };
+
+        Box::pin(async move {
+            Ok(ConditionalResponse::Modified {
+                bytes: future.await?,
+                validators: Validators::default(),
+            })
+        })
+    }
 }
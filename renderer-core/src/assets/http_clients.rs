@@ -0,0 +1,1213 @@
+use super::{
+    report_whole_response, ConditionalResponse, FetchProgress, FetchProgressCallback, HttpClient,
+    HttpClientFuture, Validators,
+};
+use futures::future::{self, FutureExt};
+use std::collections::HashMap;
+use std::future::Future;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Loads `file://` urls straight from the filesystem, for native tools that don't want to run a
+// web server.
+#[cfg(not(feature = "wasm"))]
+#[derive(Clone, Default)]
+pub struct FileHttpClient;
+
+#[cfg(not(feature = "wasm"))]
+impl HttpClient for FileHttpClient {
+    fn fetch_bytes(&self, url: &url::Url, byte_range: Option<Range<usize>>) -> HttpClientFuture {
+        use anyhow::Context;
+        use async_std::io::prelude::{ReadExt, SeekExt};
+
+        let url = url.clone();
+
+        Box::pin(async move {
+            let path = url
+                .to_file_path()
+                .map_err(|()| anyhow::anyhow!("'{}' is not a valid file url", url))?;
+
+            log::debug!("Reading {}", path.display());
+
+            let read_error = || format!("Failed to read '{}'", path.display());
+
+            let bytes = match byte_range {
+                Some(byte_range) => {
+                    let mut file = async_std::fs::File::open(&path)
+                        .await
+                        .with_context(read_error)?;
+
+                    let length = file.metadata().await.with_context(read_error)?.len();
+
+                    if byte_range.start as u64 >= length {
+                        return Err(anyhow::anyhow!(
+                            "Byte range {:?} is out of range of the {} bytes of '{}'",
+                            byte_range,
+                            length,
+                            path.display()
+                        ));
+                    }
+
+                    file.seek(std::io::SeekFrom::Start(byte_range.start as u64))
+                        .await
+                        .with_context(read_error)?;
+
+                    // Like a http server, return fewer bytes if the range goes past the end of
+                    // the file.
+                    let mut bytes = Vec::with_capacity(byte_range.len());
+                    file.take(byte_range.len() as u64)
+                        .read_to_end(&mut bytes)
+                        .await
+                        .with_context(read_error)?;
+                    bytes
+                }
+                None => async_std::fs::read(&path).await.with_context(read_error)?,
+            };
+
+            log::debug!("Got bytes for {}: {}", path.display(), bytes.len());
+
+            Ok(bytes)
+        })
+    }
+}
+
+// Serves bytes from a map of urls, so that assets can be loaded without any network or filesystem
+// access.
+#[derive(Clone, Default)]
+pub struct InMemoryHttpClient {
+    files: Arc<parking_lot::RwLock<HashMap<url::Url, Arc<[u8]>>>>,
+}
+
+impl InMemoryHttpClient {
+    pub fn new(files: HashMap<url::Url, Vec<u8>>) -> Self {
+        let client = Self::default();
+
+        for (url, bytes) in files {
+            client.insert(url, bytes);
+        }
+
+        client
+    }
+
+    pub fn insert(&self, url: url::Url, bytes: Vec<u8>) {
+        self.files.write().insert(url, bytes.into());
+    }
+
+    pub fn remove(&self, url: &url::Url) -> Option<Vec<u8>> {
+        self.files.write().remove(url).map(|bytes| bytes.to_vec())
+    }
+}
+
+impl HttpClient for InMemoryHttpClient {
+    fn fetch_bytes(&self, url: &url::Url, byte_range: Option<Range<usize>>) -> HttpClientFuture {
+        let result = match self.files.read().get(url) {
+            Some(bytes) => match byte_range {
+                Some(byte_range) if byte_range.start >= bytes.len() => Err(anyhow::anyhow!(
+                    "Byte range {:?} is out of range of the {} bytes of '{}'",
+                    byte_range,
+                    bytes.len(),
+                    url
+                )),
+                Some(byte_range) => {
+                    Ok(bytes[byte_range.start..byte_range.end.min(bytes.len())].to_vec())
+                }
+                None => Ok(bytes.to_vec()),
+            },
+            None => Err(anyhow::anyhow!("No file found for '{}'", url)),
+        };
+
+        Box::pin(async move { result })
+    }
+}
+
+type CacheKey = (url::Url, Option<Range<usize>>);
+
+struct CachedResponse {
+    bytes: Vec<u8>,
+    validators: Validators,
+}
+
+type CacheResult = Result<Arc<CachedResponse>, Arc<anyhow::Error>>;
+
+#[cfg(feature = "wasm")]
+type SharedFetch = future::Shared<std::pin::Pin<Box<dyn Future<Output = CacheResult> + 'static>>>;
+
+#[cfg(not(feature = "wasm"))]
+type SharedFetch =
+    future::Shared<std::pin::Pin<Box<dyn Future<Output = CacheResult> + Send + 'static>>>;
+
+struct CacheEntry {
+    fetch: SharedFetch,
+    // The size of the response, once it has been fetched.
+    size: usize,
+    last_used: u64,
+    // When the fetch was started, in milliseconds since the unix epoch.
+    fetched_at: f64,
+}
+
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<CacheKey, CacheEntry>,
+    size: usize,
+    // Incremented for every request, to find the least recently used entry.
+    clock: u64,
+}
+
+impl Cache {
+    fn insert(&mut self, key: CacheKey, fetch: SharedFetch) {
+        let entry = CacheEntry {
+            fetch,
+            size: 0,
+            last_used: self.clock,
+            fetched_at: now_in_ms(),
+        };
+
+        if let Some(previous) = self.entries.insert(key, entry) {
+            self.size -= previous.size;
+        }
+    }
+
+    // Remove an entry if it's still for the given fetch.
+    fn remove(&mut self, key: &CacheKey, fetch: &SharedFetch) {
+        if self.is_current(key, fetch) {
+            let entry = self.entries.remove(key).expect("just checked");
+            self.size -= entry.size;
+        }
+    }
+
+    fn retain<F: Fn(&CacheKey) -> bool>(&mut self, keep: F) {
+        let mut size = self.size;
+
+        self.entries.retain(|key, entry| {
+            let keep = keep(key);
+
+            if !keep {
+                size -= entry.size;
+            }
+
+            keep
+        });
+
+        self.size = size;
+    }
+
+    fn is_current(&self, key: &CacheKey, fetch: &SharedFetch) -> bool {
+        self.entries
+            .get(key)
+            .map(|entry| entry.fetch.ptr_eq(fetch))
+            .unwrap_or(false)
+    }
+
+    // Record the size of a fetched response and evict the least recently used responses until
+    // the cache is back under its budget. In-flight fetches are never evicted.
+    fn finish(&mut self, key: &CacheKey, fetch: &SharedFetch, size: usize, max_size: usize) {
+        if !self.is_current(key, fetch) {
+            return;
+        }
+
+        let entry = self.entries.get_mut(key).expect("just checked");
+        self.size = self.size - entry.size + size;
+        entry.size = size;
+
+        while self.size > max_size {
+            let least_recently_used = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| entry.fetch.peek().is_some())
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+
+            match least_recently_used {
+                Some(key) => {
+                    let entry = self.entries.remove(&key).expect("just found");
+                    self.size -= entry.size;
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CacheSettings {
+    // The maximum total size of the responses kept in memory. The least recently used responses
+    // are evicted when this is exceeded.
+    pub max_bytes_in_memory: usize,
+    // How long a response in memory is used for before it's revalidated with the server.
+    pub freshness: Duration,
+    // A directory to also store responses in, so that they can be reused between runs. Responses
+    // on disk are revalidated the same way as the ones in memory.
+    #[cfg(not(feature = "wasm"))]
+    pub directory: Option<std::path::PathBuf>,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            max_bytes_in_memory: 256 * 1024 * 1024,
+            freshness: Duration::from_secs(60),
+            #[cfg(not(feature = "wasm"))]
+            directory: None,
+        }
+    }
+}
+
+// Stores responses in a directory per url, with a file for the bytes of each byte range and a
+// file for the url and validators.
+#[cfg(not(feature = "wasm"))]
+#[derive(Clone)]
+struct DiskCache {
+    directory: std::path::PathBuf,
+}
+
+#[cfg(not(feature = "wasm"))]
+impl DiskCache {
+    fn url_directory(&self, url: &url::Url) -> std::path::PathBuf {
+        // FNV-1a, as the hash needs to stay the same between runs.
+        let hash = url
+            .as_str()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+            });
+
+        self.directory.join(format!("{:016x}", hash))
+    }
+
+    fn paths(&self, key: &CacheKey) -> (std::path::PathBuf, std::path::PathBuf) {
+        let name = match &key.1 {
+            Some(range) => format!("{}-{}", range.start, range.end),
+            None => "full".to_string(),
+        };
+
+        let directory = self.url_directory(&key.0);
+
+        (
+            directory.join(format!("{}.bin", name)),
+            directory.join(format!("{}.meta", name)),
+        )
+    }
+
+    async fn load(&self, key: &CacheKey) -> Option<CachedResponse> {
+        let (bytes_path, meta_path) = self.paths(key);
+
+        let meta = async_std::fs::read_to_string(&meta_path).await.ok()?;
+        let mut lines = meta.lines();
+
+        // Different urls can have the same hash.
+        if lines.next()? != key.0.as_str() {
+            return None;
+        }
+
+        let mut next_header = || {
+            lines
+                .next()
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_string())
+        };
+
+        let validators = Validators {
+            etag: next_header(),
+            last_modified: next_header(),
+        };
+
+        let bytes = async_std::fs::read(&bytes_path).await.ok()?;
+
+        Some(CachedResponse { bytes, validators })
+    }
+
+    async fn store(&self, key: &CacheKey, response: &CachedResponse) -> anyhow::Result<()> {
+        let (bytes_path, meta_path) = self.paths(key);
+
+        async_std::fs::create_dir_all(self.url_directory(&key.0)).await?;
+
+        // Write the bytes first, so that a partially stored response is never loaded.
+        async_std::fs::write(&bytes_path, &response.bytes).await?;
+
+        let meta = format!(
+            "{}\n{}\n{}\n",
+            key.0,
+            response.validators.etag.as_deref().unwrap_or_default(),
+            response
+                .validators
+                .last_modified
+                .as_deref()
+                .unwrap_or_default()
+        );
+
+        async_std::fs::write(&meta_path, meta).await?;
+
+        Ok(())
+    }
+
+    fn remove(&self, url: &url::Url) {
+        let directory = self.url_directory(url);
+
+        if let Err(error) = std::fs::remove_dir_all(&directory) {
+            if error.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to remove {}: {}", directory.display(), error);
+            }
+        }
+    }
+}
+
+// An error that is shared between multiple places, such as every request that was waiting on a
+// fetch. Its chain of causes is kept.
+#[derive(Debug)]
+pub struct SharedError(pub Arc<anyhow::Error>);
+
+impl std::fmt::Display for SharedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for SharedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+// Caches responses by url and byte range, so that assets referenced by multiple models are only
+// fetched once. Requests for a response that's still in-flight wait on the same request. If the
+// server gave an ETag or Last-Modified header, cached responses that are older than the freshness
+// window are revalidated with a conditional request before being used. Responses are kept in memory
+// up to a byte budget, and optionally in a directory on disk.
+#[derive(Clone)]
+pub struct CachingHttpClient<T> {
+    inner: T,
+    cache: Arc<parking_lot::Mutex<Cache>>,
+    max_bytes_in_memory: usize,
+    freshness: Duration,
+    #[cfg(not(feature = "wasm"))]
+    disk: Option<DiskCache>,
+}
+
+// Safety: the futures in the cache aren't `Send` on wasm. Without the atomics target feature,
+// wasm only has a single thread, so they can never be sent to or shared with another thread.
+#[cfg(all(feature = "wasm", not(target_feature = "atomics")))]
+unsafe impl<T: Send> Send for CachingHttpClient<T> {}
+#[cfg(all(feature = "wasm", not(target_feature = "atomics")))]
+unsafe impl<T: Sync> Sync for CachingHttpClient<T> {}
+
+impl<T: HttpClient> CachingHttpClient<T> {
+    pub fn new(inner: T) -> Self {
+        Self::with_settings(inner, CacheSettings::default())
+    }
+
+    pub fn with_settings(inner: T, settings: CacheSettings) -> Self {
+        Self {
+            inner,
+            cache: Default::default(),
+            max_bytes_in_memory: settings.max_bytes_in_memory,
+            freshness: settings.freshness,
+            #[cfg(not(feature = "wasm"))]
+            disk: settings.directory.map(|directory| DiskCache { directory }),
+        }
+    }
+
+    // Clear the responses in memory. Responses on disk are kept.
+    pub fn clear(&self) {
+        self.cache.lock().retain(|_| false);
+    }
+
+    // Remove all the cached responses for a url, regardless of the byte range.
+    pub fn remove(&self, url: &url::Url) {
+        self.cache.lock().retain(|(entry_url, _)| entry_url != url);
+
+        #[cfg(not(feature = "wasm"))]
+        if let Some(disk) = &self.disk {
+            disk.remove(url);
+        }
+    }
+
+    fn start_fetch(
+        &self,
+        cache: &mut Cache,
+        key: CacheKey,
+        previous: Option<Arc<CachedResponse>>,
+        progress: Option<FetchProgressCallback>,
+    ) -> SharedFetch {
+        let inner = self.inner.clone();
+        #[cfg(not(feature = "wasm"))]
+        let disk = self.disk.clone();
+        let fetch_key = key.clone();
+
+        let future = async move {
+            let key = fetch_key;
+
+            #[cfg(not(feature = "wasm"))]
+            let previous = match (previous, &disk) {
+                (None, Some(disk)) => match disk.load(&key).await {
+                    // There's no way to revalidate these, so use them as they are.
+                    Some(response) if response.validators.is_empty() => {
+                        return Ok(Arc::new(response))
+                    }
+                    response => response.map(Arc::new),
+                },
+                (previous, _) => previous,
+            };
+
+            let validators = previous
+                .as_ref()
+                .map(|previous| previous.validators.clone())
+                .unwrap_or_default();
+
+            let result = inner
+                .fetch_bytes_if_modified(&key.0, key.1.clone(), &validators, progress)
+                .await;
+
+            match (result, previous) {
+                (Ok(ConditionalResponse::Modified { bytes, validators }), _) => {
+                    let response = Arc::new(CachedResponse { bytes, validators });
+
+                    #[cfg(not(feature = "wasm"))]
+                    if let Some(disk) = &disk {
+                        if let Err(error) = disk.store(&key, &response).await {
+                            log::warn!("Failed to store '{}' on disk: {:#}", key.0, error);
+                        }
+                    }
+
+                    Ok(response)
+                }
+                (Ok(ConditionalResponse::NotModified), Some(previous)) => Ok(previous),
+                (Ok(ConditionalResponse::NotModified), None) => Err(Arc::new(anyhow::anyhow!(
+                    "Got a not-modified response for '{}' without a cached response",
+                    key.0
+                ))),
+                // We'd rather use a potentially stale response than none at all.
+                (Err(error), Some(previous)) => {
+                    log::warn!(
+                        "Failed to revalidate '{}', using the cached response: {:#}",
+                        key.0,
+                        error
+                    );
+                    Ok(previous)
+                }
+                (Err(error), None) => Err(Arc::new(error)),
+            }
+        };
+
+        #[cfg(feature = "wasm")]
+        let future = future.boxed_local().shared();
+
+        #[cfg(not(feature = "wasm"))]
+        let future = future.boxed().shared();
+
+        cache.insert(key, future.clone());
+
+        future
+    }
+}
+
+impl<T: HttpClient> CachingHttpClient<T> {
+    fn fetch(
+        &self,
+        url: &url::Url,
+        range: Option<Range<usize>>,
+        progress: Option<FetchProgressCallback>,
+    ) -> HttpClientFuture {
+        let key = (url.clone(), range.clone());
+
+        // Only a request that starts a fetch gets to see the response streamed in. Requests that
+        // wait on an in-flight fetch or get a cached response have all of it reported at once.
+        let streamed = Arc::new(AtomicBool::new(false));
+        let streaming_progress = progress.clone().map(|progress| -> FetchProgressCallback {
+            let streamed = streamed.clone();
+
+            Arc::new(move |event| {
+                streamed.store(true, Ordering::Relaxed);
+                progress(event);
+            })
+        });
+
+        let fetch = {
+            let mut cache = self.cache.lock();
+            cache.clock += 1;
+            let clock = cache.clock;
+
+            let freshness_in_ms = self.freshness.as_secs_f64() * 1000.0;
+
+            let existing = cache.entries.get_mut(&key).map(|entry| {
+                entry.last_used = clock;
+                let fresh = now_in_ms() - entry.fetched_at < freshness_in_ms;
+                (entry.fetch.clone(), entry.fetch.peek().cloned(), fresh)
+            });
+
+            match existing {
+                Some((_, Some(Ok(cached)), false)) if !cached.validators.is_empty() => {
+                    self.start_fetch(&mut cache, key.clone(), Some(cached), streaming_progress)
+                }
+                // Either still in-flight, fresh or cached without a way to revalidate it.
+                Some((fetch, ..)) => fetch,
+                None => self.start_fetch(&mut cache, key.clone(), None, streaming_progress),
+            }
+        };
+
+        let cache = self.cache.clone();
+        let max_bytes_in_memory = self.max_bytes_in_memory;
+
+        Box::pin(async move {
+            match fetch.clone().await {
+                Ok(cached) => {
+                    cache
+                        .lock()
+                        .finish(&key, &fetch, cached.bytes.len(), max_bytes_in_memory);
+
+                    if let Some(progress) = &progress {
+                        if !streamed.load(Ordering::Relaxed) {
+                            report_whole_response(progress, cached.bytes.len());
+                        }
+                    }
+
+                    Ok(cached.bytes.clone())
+                }
+                Err(error) => {
+                    // Remove the failed entry so that the next request tries again.
+                    cache.lock().remove(&key, &fetch);
+
+                    Err(anyhow::Error::new(SharedError(error)))
+                }
+            }
+        })
+    }
+}
+
+impl<T: HttpClient> HttpClient for CachingHttpClient<T> {
+    fn fetch_bytes(&self, url: &url::Url, range: Option<Range<usize>>) -> HttpClientFuture {
+        self.fetch(url, range, None)
+    }
+
+    fn fetch_bytes_with_progress(
+        &self,
+        url: &url::Url,
+        range: Option<Range<usize>>,
+        progress: FetchProgressCallback,
+    ) -> HttpClientFuture {
+        self.fetch(url, range, Some(progress))
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+fn now_in_ms() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64() * 1000.0)
+        .unwrap_or(0.0)
+}
+
+// `SystemTime::now` panics on wasm.
+#[cfg(feature = "wasm")]
+fn now_in_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RetrySettings {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    // How long a single request can take before it's considered failed.
+    pub timeout: Duration,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(4),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+// Retries failed or timed out requests with an exponential backoff.
+#[derive(Clone)]
+pub struct RetryingHttpClient<T> {
+    inner: T,
+    pub settings: RetrySettings,
+}
+
+impl<T: HttpClient> RetryingHttpClient<T> {
+    pub fn new(inner: T, settings: RetrySettings) -> Self {
+        Self { inner, settings }
+    }
+}
+
+impl<T: HttpClient> HttpClient for RetryingHttpClient<T> {
+    fn fetch_bytes(&self, url: &url::Url, range: Option<Range<usize>>) -> HttpClientFuture {
+        let inner = self.inner.clone();
+        let fetch_url = url.clone();
+
+        with_retries(self.settings, url.clone(), move || {
+            inner.fetch_bytes(&fetch_url, range.clone())
+        })
+    }
+
+    fn fetch_bytes_with_progress(
+        &self,
+        url: &url::Url,
+        range: Option<Range<usize>>,
+        progress: FetchProgressCallback,
+    ) -> HttpClientFuture {
+        let inner = self.inner.clone();
+        let fetch_url = url.clone();
+        let progress = RetriedProgress::new(progress);
+
+        with_retries(self.settings, url.clone(), move || {
+            inner.fetch_bytes_with_progress(&fetch_url, range.clone(), progress.attempt())
+        })
+    }
+
+    fn fetch_bytes_if_modified(
+        &self,
+        url: &url::Url,
+        range: Option<Range<usize>>,
+        validators: &Validators,
+        progress: Option<FetchProgressCallback>,
+    ) -> HttpClientFuture<ConditionalResponse> {
+        let inner = self.inner.clone();
+        let fetch_url = url.clone();
+        let validators = validators.clone();
+        let progress = progress.map(RetriedProgress::new);
+
+        with_retries(self.settings, url.clone(), move || {
+            inner.fetch_bytes_if_modified(
+                &fetch_url,
+                range.clone(),
+                &validators,
+                progress.as_ref().map(|progress| progress.attempt()),
+            )
+        })
+    }
+}
+
+// Each attempt fetches the response from the start again, so only the bytes past the furthest
+// that any attempt got are passed on.
+struct RetriedProgress {
+    inner: FetchProgressCallback,
+    reported_content_length: AtomicBool,
+    fetched: AtomicU64,
+}
+
+impl RetriedProgress {
+    fn new(inner: FetchProgressCallback) -> Arc<Self> {
+        Arc::new(Self {
+            inner,
+            reported_content_length: AtomicBool::new(false),
+            fetched: AtomicU64::new(0),
+        })
+    }
+
+    fn attempt(self: &Arc<Self>) -> FetchProgressCallback {
+        let retried = self.clone();
+        let fetched_by_attempt = AtomicU64::new(0);
+
+        Arc::new(move |event| match event {
+            FetchProgress::ContentLength(_) => {
+                if !retried
+                    .reported_content_length
+                    .swap(true, Ordering::Relaxed)
+                {
+                    (retried.inner)(event);
+                }
+            }
+            FetchProgress::Chunk(bytes) => {
+                let fetched = fetched_by_attempt.fetch_add(bytes, Ordering::Relaxed) + bytes;
+                let previously_fetched = retried.fetched.fetch_max(fetched, Ordering::Relaxed);
+
+                if fetched > previously_fetched {
+                    (retried.inner)(FetchProgress::Chunk(fetched - previously_fetched));
+                }
+            }
+        })
+    }
+}
+
+fn with_retries<R: Send + 'static, F: Fn() -> HttpClientFuture<R> + Send + 'static>(
+    settings: RetrySettings,
+    url: url::Url,
+    fetch: F,
+) -> HttpClientFuture<R> {
+    Box::pin(async move {
+        let mut backoff = settings.initial_backoff;
+        let mut retries = 0;
+
+        loop {
+            let error = match async_std::future::timeout(settings.timeout, fetch()).await {
+                Ok(Ok(value)) => return Ok(value),
+                Ok(Err(error)) => error,
+                Err(_) => anyhow::anyhow!("Timed out after {:?}", settings.timeout),
+            };
+
+            if retries >= settings.max_retries {
+                return Err(error.context(format!(
+                    "Request for '{}' failed after {} retries",
+                    url, retries
+                )));
+            }
+
+            retries += 1;
+
+            log::warn!(
+                "Request for '{}' failed: {}. Retrying in {:?} ({}/{})",
+                url,
+                error,
+                backoff,
+                retries,
+                settings.max_retries
+            );
+
+            async_std::task::sleep(backoff).await;
+
+            backoff = (backoff * 2).min(settings.max_backoff);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fetch(client: &impl HttpClient, url: &url::Url, range: Option<Range<usize>>) -> Vec<u8> {
+        futures::executor::block_on(client.fetch_bytes(url, range)).unwrap()
+    }
+
+    fn fetch_error(
+        client: &impl HttpClient,
+        url: &url::Url,
+        range: Option<Range<usize>>,
+    ) -> anyhow::Error {
+        futures::executor::block_on(client.fetch_bytes(url, range)).unwrap_err()
+    }
+
+    fn in_memory_client() -> (InMemoryHttpClient, url::Url) {
+        let url = url::Url::parse("http://localhost/model.bin").unwrap();
+
+        (
+            InMemoryHttpClient::new(HashMap::from([(url.clone(), (0..10).collect())])),
+            url,
+        )
+    }
+
+    #[test]
+    fn in_memory_byte_ranges() {
+        let (client, url) = in_memory_client();
+
+        assert_eq!(fetch(&client, &url, None), (0..10).collect::<Vec<u8>>());
+        assert_eq!(fetch(&client, &url, Some(2..5)), vec![2, 3, 4]);
+        // Ranges that go past the end are cut short.
+        assert_eq!(fetch(&client, &url, Some(8..20)), vec![8, 9]);
+    }
+
+    #[test]
+    fn in_memory_out_of_range() {
+        let (client, url) = in_memory_client();
+
+        let error = fetch_error(&client, &url, Some(10..12));
+        assert!(error.to_string().contains("out of range"), "{}", error);
+    }
+
+    #[test]
+    fn in_memory_missing_url() {
+        let (client, _) = in_memory_client();
+        let missing = url::Url::parse("http://localhost/missing.bin").unwrap();
+
+        let error = fetch_error(&client, &missing, None);
+        assert!(error.to_string().contains("No file found"), "{}", error);
+    }
+
+    #[test]
+    fn caching_evicts_least_recently_used() {
+        let inner = InMemoryHttpClient::default();
+        let urls: Vec<_> = (0..3)
+            .map(|i| url::Url::parse(&format!("http://localhost/{}.bin", i)).unwrap())
+            .collect();
+
+        for url in &urls {
+            inner.insert(url.clone(), vec![0; 10]);
+        }
+
+        let client = CachingHttpClient::with_settings(
+            inner.clone(),
+            CacheSettings {
+                max_bytes_in_memory: 20,
+                ..Default::default()
+            },
+        );
+
+        fetch(&client, &urls[0], None);
+        fetch(&client, &urls[1], None);
+        // Use the first response again so that the second one is evicted instead.
+        fetch(&client, &urls[0], None);
+        fetch(&client, &urls[2], None);
+
+        for url in &urls {
+            inner.remove(url);
+        }
+
+        assert_eq!(fetch(&client, &urls[0], None), vec![0; 10]);
+        assert_eq!(fetch(&client, &urls[2], None), vec![0; 10]);
+        fetch_error(&client, &urls[1], None);
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    #[test]
+    fn caching_on_disk() {
+        let directory =
+            std::env::temp_dir().join(format!("superconductor-disk-cache-{}", std::process::id()));
+        let (inner, url) = in_memory_client();
+
+        let settings = CacheSettings {
+            directory: Some(directory.clone()),
+            ..Default::default()
+        };
+
+        fetch(
+            &CachingHttpClient::with_settings(inner.clone(), settings.clone()),
+            &url,
+            Some(2..5),
+        );
+
+        inner.remove(&url);
+
+        let client = CachingHttpClient::with_settings(inner, settings);
+        assert_eq!(fetch(&client, &url, Some(2..5)), vec![2, 3, 4]);
+        fetch_error(&client, &url, None);
+
+        client.remove(&url);
+        fetch_error(&client, &url, Some(2..5));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    enum ScriptedResponse {
+        Modified(Vec<u8>, Validators),
+        NotModified,
+        Error,
+        // Reports a content length and a chunk of that many bytes before failing.
+        PartialError { content_length: u64, chunk: u64 },
+        // Never responds, so that the request times out.
+        Hang,
+    }
+
+    // Stands in for a server, giving each request the next scripted response and recording the
+    // validators that were sent.
+    #[derive(Clone, Default)]
+    struct ScriptedHttpClient {
+        responses: Arc<parking_lot::Mutex<std::collections::VecDeque<ScriptedResponse>>>,
+        requests: Arc<parking_lot::Mutex<Vec<Validators>>>,
+    }
+
+    impl ScriptedHttpClient {
+        fn new(responses: Vec<ScriptedResponse>) -> Self {
+            Self {
+                responses: Arc::new(parking_lot::Mutex::new(responses.into())),
+                requests: Default::default(),
+            }
+        }
+
+        fn push(&self, response: ScriptedResponse) {
+            self.responses.lock().push_back(response);
+        }
+
+        fn requests(&self) -> Vec<Validators> {
+            self.requests.lock().clone()
+        }
+    }
+
+    impl HttpClient for ScriptedHttpClient {
+        fn fetch_bytes(&self, url: &url::Url, range: Option<Range<usize>>) -> HttpClientFuture {
+            let future = self.fetch_bytes_if_modified(url, range, &Validators::default(), None);
+
+            Box::pin(async move {
+                match future.await? {
+                    ConditionalResponse::Modified { bytes, .. } => Ok(bytes),
+                    ConditionalResponse::NotModified => Err(anyhow::anyhow!("Not modified")),
+                }
+            })
+        }
+
+        fn fetch_bytes_with_progress(
+            &self,
+            url: &url::Url,
+            range: Option<Range<usize>>,
+            progress: FetchProgressCallback,
+        ) -> HttpClientFuture {
+            let future =
+                self.fetch_bytes_if_modified(url, range, &Validators::default(), Some(progress));
+
+            Box::pin(async move {
+                match future.await? {
+                    ConditionalResponse::Modified { bytes, .. } => Ok(bytes),
+                    ConditionalResponse::NotModified => Err(anyhow::anyhow!("Not modified")),
+                }
+            })
+        }
+
+        fn fetch_bytes_if_modified(
+            &self,
+            _url: &url::Url,
+            _range: Option<Range<usize>>,
+            validators: &Validators,
+            progress: Option<FetchProgressCallback>,
+        ) -> HttpClientFuture<ConditionalResponse> {
+            self.requests.lock().push(validators.clone());
+
+            let report = |event| {
+                if let Some(progress) = &progress {
+                    progress(event);
+                }
+            };
+
+            match self.responses.lock().pop_front() {
+                Some(ScriptedResponse::Modified(bytes, validators)) => {
+                    report(FetchProgress::ContentLength(bytes.len() as u64));
+                    report(FetchProgress::Chunk(bytes.len() as u64));
+
+                    Box::pin(async move { Ok(ConditionalResponse::Modified { bytes, validators }) })
+                }
+                Some(ScriptedResponse::NotModified) => {
+                    Box::pin(async { Ok(ConditionalResponse::NotModified) })
+                }
+                Some(ScriptedResponse::Error) => {
+                    Box::pin(async { Err(anyhow::anyhow!("Scripted error")) })
+                }
+                Some(ScriptedResponse::PartialError {
+                    content_length,
+                    chunk,
+                }) => {
+                    report(FetchProgress::ContentLength(content_length));
+                    report(FetchProgress::Chunk(chunk));
+                    Box::pin(async { Err(anyhow::anyhow!("Scripted error")) })
+                }
+                Some(ScriptedResponse::Hang) => Box::pin(future::pending()),
+                None => Box::pin(async { Err(anyhow::anyhow!("No scripted response left")) }),
+            }
+        }
+    }
+
+    fn etag(etag: &str) -> Validators {
+        Validators {
+            etag: Some(etag.to_string()),
+            last_modified: None,
+        }
+    }
+
+    fn test_url() -> url::Url {
+        url::Url::parse("http://localhost/model.bin").unwrap()
+    }
+
+    fn retry_settings() -> RetrySettings {
+        RetrySettings {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(5),
+            max_backoff: Duration::from_millis(20),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn retrying_backs_off_between_failures() {
+        let inner = ScriptedHttpClient::new(vec![
+            ScriptedResponse::Error,
+            ScriptedResponse::Error,
+            ScriptedResponse::Modified(vec![1, 2, 3], Validators::default()),
+        ]);
+        let client = RetryingHttpClient::new(inner.clone(), retry_settings());
+
+        let start = std::time::Instant::now();
+        assert_eq!(fetch(&client, &test_url(), None), vec![1, 2, 3]);
+
+        // Backing off for 5ms and then 10ms.
+        assert!(start.elapsed() >= Duration::from_millis(15));
+        assert_eq!(inner.requests().len(), 3);
+    }
+
+    #[test]
+    fn retrying_gives_up_after_max_retries() {
+        let inner = ScriptedHttpClient::new(vec![
+            ScriptedResponse::Error,
+            ScriptedResponse::Error,
+            ScriptedResponse::Modified(vec![1, 2, 3], Validators::default()),
+        ]);
+        let client = RetryingHttpClient::new(
+            inner.clone(),
+            RetrySettings {
+                max_retries: 1,
+                ..retry_settings()
+            },
+        );
+
+        let error = fetch_error(&client, &test_url(), None);
+        assert!(error.to_string().contains("after 1 retries"), "{}", error);
+        assert_eq!(inner.requests().len(), 2);
+    }
+
+    #[test]
+    fn retrying_times_out_requests() {
+        let inner = ScriptedHttpClient::new(vec![
+            ScriptedResponse::Hang,
+            ScriptedResponse::Modified(vec![1, 2, 3], Validators::default()),
+        ]);
+        let client = RetryingHttpClient::new(
+            inner.clone(),
+            RetrySettings {
+                timeout: Duration::from_millis(10),
+                ..retry_settings()
+            },
+        );
+
+        assert_eq!(fetch(&client, &test_url(), None), vec![1, 2, 3]);
+        assert_eq!(inner.requests().len(), 2);
+    }
+
+    fn recording_progress() -> (
+        FetchProgressCallback,
+        Arc<parking_lot::Mutex<Vec<FetchProgress>>>,
+    ) {
+        let events = Arc::new(parking_lot::Mutex::new(Vec::new()));
+
+        let progress: FetchProgressCallback = {
+            let events = events.clone();
+            Arc::new(move |event| events.lock().push(event))
+        };
+
+        (progress, events)
+    }
+
+    #[test]
+    fn retrying_reports_progress_once() {
+        let inner = ScriptedHttpClient::new(vec![
+            ScriptedResponse::PartialError {
+                content_length: 3,
+                chunk: 2,
+            },
+            ScriptedResponse::Modified(vec![1, 2, 3], Validators::default()),
+        ]);
+        let client = RetryingHttpClient::new(inner, retry_settings());
+        let (progress, events) = recording_progress();
+
+        let bytes = futures::executor::block_on(client.fetch_bytes_with_progress(
+            &test_url(),
+            None,
+            progress,
+        ))
+        .unwrap();
+
+        assert_eq!(bytes, vec![1, 2, 3]);
+        assert_eq!(
+            *events.lock(),
+            vec![
+                FetchProgress::ContentLength(3),
+                FetchProgress::Chunk(2),
+                FetchProgress::Chunk(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn caching_reports_progress_for_cached_responses() {
+        let inner =
+            ScriptedHttpClient::new(vec![ScriptedResponse::Modified(vec![1, 2, 3], etag("a"))]);
+        let client = CachingHttpClient::new(inner.clone());
+        let url = test_url();
+
+        for _ in 0..2 {
+            let (progress, events) = recording_progress();
+
+            futures::executor::block_on(client.fetch_bytes_with_progress(&url, None, progress))
+                .unwrap();
+
+            assert_eq!(
+                *events.lock(),
+                vec![FetchProgress::ContentLength(3), FetchProgress::Chunk(3)]
+            );
+        }
+
+        assert_eq!(inner.requests().len(), 1);
+    }
+
+    #[test]
+    fn caching_revalidates_stale_responses() {
+        let inner = ScriptedHttpClient::new(vec![
+            ScriptedResponse::Modified(vec![1, 2, 3], etag("a")),
+            ScriptedResponse::NotModified,
+        ]);
+        let client = CachingHttpClient::with_settings(
+            inner.clone(),
+            CacheSettings {
+                freshness: Duration::ZERO,
+                ..Default::default()
+            },
+        );
+        let url = test_url();
+
+        assert_eq!(fetch(&client, &url, None), vec![1, 2, 3]);
+        // The server says that the response hasn't changed, so the cached bytes are used.
+        assert_eq!(fetch(&client, &url, None), vec![1, 2, 3]);
+
+        inner.push(ScriptedResponse::Modified(vec![4, 5], etag("b")));
+        assert_eq!(fetch(&client, &url, None), vec![4, 5]);
+
+        assert_eq!(
+            inner.requests(),
+            vec![Validators::default(), etag("a"), etag("a")]
+        );
+    }
+
+    #[test]
+    fn caching_uses_fresh_responses_without_revalidating() {
+        let inner =
+            ScriptedHttpClient::new(vec![ScriptedResponse::Modified(vec![1, 2, 3], etag("a"))]);
+        let client = CachingHttpClient::with_settings(
+            inner.clone(),
+            CacheSettings {
+                freshness: Duration::from_secs(60 * 60),
+                ..Default::default()
+            },
+        );
+        let url = test_url();
+
+        assert_eq!(fetch(&client, &url, None), vec![1, 2, 3]);
+        assert_eq!(fetch(&client, &url, None), vec![1, 2, 3]);
+        assert_eq!(inner.requests().len(), 1);
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn file_client() -> (FileHttpClient, url::Url) {
+        let path = std::env::temp_dir().join(format!(
+            "superconductor-file-client-{}.bin",
+            std::process::id()
+        ));
+        std::fs::write(&path, (0..10).collect::<Vec<u8>>()).unwrap();
+
+        (FileHttpClient, url::Url::from_file_path(&path).unwrap())
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    #[test]
+    fn file_byte_ranges() {
+        let (client, url) = file_client();
+
+        assert_eq!(fetch(&client, &url, None), (0..10).collect::<Vec<u8>>());
+        assert_eq!(fetch(&client, &url, Some(2..5)), vec![2, 3, 4]);
+        assert_eq!(fetch(&client, &url, Some(8..20)), vec![8, 9]);
+
+        let error = fetch_error(&client, &url, Some(10..12));
+        assert!(error.to_string().contains("out of range"), "{}", error);
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    #[test]
+    fn file_missing_url() {
+        let url = url::Url::from_file_path(std::env::temp_dir().join("superconductor-missing.bin"))
+            .unwrap();
+
+        let error = fetch_error(&FileHttpClient, &url, None);
+        assert!(error.to_string().contains("Failed to read"), "{}", error);
+    }
+}
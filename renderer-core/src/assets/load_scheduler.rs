@@ -0,0 +1,209 @@
+use std::cmp::Ordering as CmpOrdering;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+// The priority of a load. Loads with a higher explicit priority go first, then loads that are
+// closer to the camera. This is shared with the loading futures so that it can be updated every
+// frame while they wait.
+#[derive(Debug)]
+pub struct LoadPriority {
+    explicit: AtomicI32,
+    distance: AtomicU32,
+}
+
+impl Default for LoadPriority {
+    fn default() -> Self {
+        Self {
+            explicit: AtomicI32::new(0),
+            distance: AtomicU32::new(f32::INFINITY.to_bits()),
+        }
+    }
+}
+
+impl LoadPriority {
+    pub fn set(&self, explicit: i32, distance: f32) {
+        self.explicit.store(explicit, Ordering::Relaxed);
+        self.distance.store(distance.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> (i32, f32) {
+        (
+            self.explicit.load(Ordering::Relaxed),
+            f32::from_bits(self.distance.load(Ordering::Relaxed)),
+        )
+    }
+}
+
+// Browsers only make 6 connections to a server at once over HTTP/1.1, so starting more loads than
+// this just queues them in the browser, where their priorities aren't taken into account.
+pub const DEFAULT_MAX_CONCURRENT_LOADS: usize = 6;
+
+// Limits the number of loads that run at once, handing out permits to the waiting load with the
+// highest priority whenever a permit is returned.
+#[derive(Clone)]
+pub struct LoadScheduler {
+    state: Arc<parking_lot::Mutex<SchedulerState>>,
+}
+
+struct SchedulerState {
+    max_concurrent_loads: usize,
+    active_loads: usize,
+    next_id: u64,
+    waiting: Vec<WaitingLoad>,
+}
+
+struct WaitingLoad {
+    id: u64,
+    priority: Arc<LoadPriority>,
+    waker: Waker,
+}
+
+impl SchedulerState {
+    fn highest_priority_index(&self) -> Option<usize> {
+        self.waiting
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| {
+                let (a_explicit, a_distance) = a.priority.get();
+                let (b_explicit, b_distance) = b.priority.get();
+
+                a_explicit
+                    .cmp(&b_explicit)
+                    .then(
+                        b_distance
+                            .partial_cmp(&a_distance)
+                            .unwrap_or(CmpOrdering::Equal),
+                    )
+                    // Fall back to first-come, first-served.
+                    .then(b.id.cmp(&a.id))
+            })
+            .map(|(index, _)| index)
+    }
+
+    fn wake_highest_priority(&self) {
+        if self.active_loads < self.max_concurrent_loads {
+            if let Some(index) = self.highest_priority_index() {
+                self.waiting[index].waker.wake_by_ref();
+            }
+        }
+    }
+}
+
+impl LoadScheduler {
+    pub fn new(max_concurrent_loads: usize) -> Self {
+        Self {
+            state: Arc::new(parking_lot::Mutex::new(SchedulerState {
+                max_concurrent_loads: max_concurrent_loads.max(1),
+                active_loads: 0,
+                next_id: 0,
+                waiting: Vec::new(),
+            })),
+        }
+    }
+
+    pub fn set_max_concurrent_loads(&self, max_concurrent_loads: usize) {
+        let mut state = self.state.lock();
+        state.max_concurrent_loads = max_concurrent_loads.max(1);
+        state.wake_highest_priority();
+    }
+
+    pub fn num_active_loads(&self) -> usize {
+        self.state.lock().active_loads
+    }
+
+    pub fn num_waiting_loads(&self) -> usize {
+        self.state.lock().waiting.len()
+    }
+
+    // Wait until the load is allowed to start. The load should hold onto the permit until it has
+    // finished.
+    pub fn acquire(&self, priority: Arc<LoadPriority>) -> Acquire {
+        Acquire {
+            scheduler: self.clone(),
+            priority,
+            id: None,
+        }
+    }
+}
+
+pub struct Acquire {
+    scheduler: LoadScheduler,
+    priority: Arc<LoadPriority>,
+    id: Option<u64>,
+}
+
+impl Future for Acquire {
+    type Output = LoadPermit;
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<LoadPermit> {
+        let scheduler = self.scheduler.clone();
+        let mut state = scheduler.state.lock();
+
+        let id = match self.id {
+            Some(id) => {
+                if let Some(waiting) = state.waiting.iter_mut().find(|waiting| waiting.id == id) {
+                    waiting.waker = context.waker().clone();
+                }
+                id
+            }
+            None => {
+                let id = state.next_id;
+                state.next_id += 1;
+                state.waiting.push(WaitingLoad {
+                    id,
+                    priority: self.priority.clone(),
+                    waker: context.waker().clone(),
+                });
+                self.id = Some(id);
+                id
+            }
+        };
+
+        if state.active_loads >= state.max_concurrent_loads {
+            return Poll::Pending;
+        }
+
+        match state.highest_priority_index() {
+            Some(index) if state.waiting[index].id == id => {
+                state.waiting.remove(index);
+                state.active_loads += 1;
+                self.id = None;
+
+                Poll::Ready(LoadPermit {
+                    scheduler: self.scheduler.clone(),
+                })
+            }
+            // The priorities can change while waiting, so a permit might be free that we aren't
+            // the best candidate for.
+            _ => {
+                state.wake_highest_priority();
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            let mut state = self.scheduler.state.lock();
+            state.waiting.retain(|waiting| waiting.id != id);
+            state.wake_highest_priority();
+        }
+    }
+}
+
+pub struct LoadPermit {
+    scheduler: LoadScheduler,
+}
+
+impl Drop for LoadPermit {
+    fn drop(&mut self) {
+        let mut state = self.scheduler.state.lock();
+        state.active_loads -= 1;
+        state.wake_highest_priority();
+    }
+}
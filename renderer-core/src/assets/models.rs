@@ -1,26 +1,34 @@
+use super::load_scheduler::{LoadPriority, LoadScheduler};
 use super::textures;
 use super::HttpClient;
+use crate::buffers::Relocations;
 use crate::culling::{BoundingBox, BoundingSphere};
 use crate::permutations;
-use crate::BindGroupLayouts;
+use crate::{BindGroupLayouts, Texture};
 use base64::Engine;
 use glam::{Mat4, UVec4, Vec2, Vec3, Vec4};
 use gltf_helpers::{
-    animation::{read_animations, Animation, AnimationJoints},
-    Extensions, Similarity,
+    animation::{read_animations, Animation, AnimationJoints, MorphWeights},
+    Extensions, Transform,
 };
 use goth_gltf::extensions::CompressionMode;
 use goth_gltf::AlphaMode;
 use std::collections::{HashMap, HashSet};
 use std::ops::Range;
 use std::sync::Arc;
-use texture_loading::MaterialBindGroup;
+pub use texture_loading::TextureCache;
+use texture_loading::{MaterialBindGroup, MaterialTextureLoads};
 
+mod accessors;
+mod progress;
+mod scene_objects;
 mod texture_loading;
 
-use goth_gltf::primitive_reader::{
-    read_buffer_with_accessor, read_f32, read_f32x3, read_f32x4, PrimitiveReader,
-};
+pub use progress::{LoadProgress, LoadProgressSnapshot};
+pub use scene_objects::{Camera, Light, LightKind, Projection, SceneObjects};
+
+use accessors::AccessorReader;
+use std::borrow::Cow;
 use texture_loading::start_loading_all_material_textures;
 
 #[derive(Clone)]
@@ -34,6 +42,12 @@ pub struct Context<T> {
     pub index_buffer: Arc<crate::buffers::IndexBuffer>,
     pub pipelines: Arc<crate::Pipelines>,
     pub texture_settings: textures::Settings,
+    pub texture_cache: TextureCache,
+    pub progress: Arc<LoadProgress>,
+    pub load_scheduler: LoadScheduler,
+    pub load_priority: Arc<LoadPriority>,
+    // Bound by animated models without any morph targets.
+    pub empty_morph_target_texture: Arc<Texture>,
 }
 
 impl<T: Clone> Context<T> {
@@ -125,14 +139,16 @@ fn collect_primitives<
         primitives.push(Primitive {
             bounding_box: staging_primitive.bounding_box,
             bounding_sphere: staging_primitive.bounding_sphere,
-            transform: staging_primitive.transform,
+            transforms: staging_primitive.transforms.clone(),
             screen_coverages: staging_primitive.screen_coverages.clone(),
+            morph_targets: staging_primitive.morph_targets,
             lods: staging_primitive
                 .lods
                 .iter()
                 .map(|lod| PrimitiveLod {
                     index_buffer_range: staging_buffers.collect(&lod.buffers),
                     material_index: lod.material_index,
+                    variant_material_indices: lod.variant_material_indices.clone(),
                     is_lightmapped: lod.buffers.is_lightmapped(),
                 })
                 .collect(),
@@ -151,9 +167,368 @@ fn collect_primitives<
 pub struct AnimatedModelData {
     pub animations: Vec<Animation>,
     pub depth_first_nodes: gltf_helpers::DepthFirstNodes,
-    pub inverse_bind_transforms: Vec<Similarity>,
+    pub inverse_bind_transforms: Vec<Transform>,
     pub joint_indices_to_node_indices: Vec<usize>,
     pub animation_joints: AnimationJoints,
+    pub morph_weights: MorphWeights,
+    // Whether the joints need to be uploaded as affine transforms instead of packed similarities.
+    pub affine_joints: bool,
+}
+
+// The joints of every skin in a model, laid out one after the other so that all the skins of an
+// instance can be uploaded together.
+struct Joints {
+    joint_indices_to_node_indices: Vec<usize>,
+    inverse_bind_transforms: Vec<Transform>,
+    // For skinned nodes, the offset of the node's skin in the joints. For unskinned nodes, the
+    // index of a joint that follows the node itself.
+    node_offsets: Vec<u32>,
+}
+
+impl Joints {
+    fn new(
+        gltf: &goth_gltf::Gltf<Extensions>,
+        accessor_reader: &AccessorReader,
+    ) -> anyhow::Result<Self> {
+        // Without any skins, every node is a joint with an identity inverse bind transform.
+        if gltf.skins.is_empty() {
+            return Ok(Self {
+                joint_indices_to_node_indices: (0..gltf.nodes.len()).collect(),
+                inverse_bind_transforms: vec![Transform::IDENTITY; gltf.nodes.len()],
+                node_offsets: (0..gltf.nodes.len() as u32).collect(),
+            });
+        }
+
+        let mut joint_indices_to_node_indices = Vec::new();
+        let mut inverse_bind_transforms = Vec::new();
+        let mut skin_offsets = Vec::with_capacity(gltf.skins.len());
+
+        for skin in &gltf.skins {
+            skin_offsets.push(joint_indices_to_node_indices.len() as u32);
+
+            joint_indices_to_node_indices.extend_from_slice(&skin.joints);
+
+            match skin.inverse_bind_matrices {
+                Some(accessor_index) => {
+                    let matrices = accessor_reader.read_f32x16(&gltf.accessors[accessor_index])?;
+
+                    if matrices.len() != skin.joints.len() {
+                        return Err(anyhow::anyhow!(
+                            "Skin has {} joints but {} inverse bind matrices",
+                            skin.joints.len(),
+                            matrices.len()
+                        ));
+                    }
+
+                    inverse_bind_transforms.extend(
+                        matrices
+                            .iter()
+                            .map(|matrix| Transform::new_from_mat4(Mat4::from_cols_array(matrix))),
+                    );
+                }
+                // The spec says that the matrices are assumed to be identity matrices when
+                // the accessor is missing.
+                None => inverse_bind_transforms
+                    .extend(std::iter::repeat(Transform::IDENTITY).take(skin.joints.len())),
+            }
+        }
+
+        let mut node_offsets = vec![0; gltf.nodes.len()];
+
+        for (node_index, node) in gltf.nodes.iter().enumerate() {
+            match node.skin {
+                Some(skin_index) => {
+                    node_offsets[node_index] = *skin_offsets.get(skin_index).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Node {} references missing skin {}",
+                            node_index,
+                            skin_index
+                        )
+                    })?;
+                }
+                None if node.mesh.is_some() => {
+                    node_offsets[node_index] = joint_indices_to_node_indices.len() as u32;
+                    joint_indices_to_node_indices.push(node_index);
+                    inverse_bind_transforms.push(Transform::IDENTITY);
+                }
+                None => {}
+            }
+        }
+
+        Ok(Self {
+            joint_indices_to_node_indices,
+            inverse_bind_transforms,
+            node_offsets,
+        })
+    }
+}
+
+// The names of the model's `KHR_materials_variants` variants.
+fn read_variants(gltf: &goth_gltf::Gltf<Extensions>) -> Vec<String> {
+    gltf.extensions
+        .khr_materials_variants
+        .as_ref()
+        .map(|extension| {
+            extension
+                .variants
+                .iter()
+                .map(|variant| variant.name.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Map each of the model's `KHR_materials_variants` variants to the material the primitive uses for
+// it. Variants without a mapping use the primitive's default material.
+//
+// Primitives are sorted into pipelines by their default material, so mappings to materials that
+// would need a different pipeline (by changing the alpha mode or double-sidedness) aren't
+// supported.
+fn read_variant_material_indices(
+    primitive: &goth_gltf::Primitive<Extensions>,
+    materials: &[goth_gltf::Material<Extensions>],
+    default_material_index: usize,
+    num_variants: usize,
+) -> anyhow::Result<Vec<usize>> {
+    let mut material_indices = vec![default_material_index; num_variants];
+
+    let extension = match &primitive.extensions.khr_materials_variants {
+        Some(extension) => extension,
+        None => return Ok(material_indices),
+    };
+
+    let default_material = materials.get(default_material_index).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Material {} is out of range of {}",
+            default_material_index,
+            materials.len()
+        )
+    })?;
+
+    for mapping in &extension.mappings {
+        let material = materials.get(mapping.material).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Variant mapping to material {} is out of range of {}",
+                mapping.material,
+                materials.len()
+            )
+        })?;
+
+        if !uses_same_pipeline(material, default_material) {
+            return Err(anyhow::anyhow!(
+                "Variant mapping from material {} to material {} changes the alpha mode or \
+                 double-sidedness, which isn't supported",
+                default_material_index,
+                mapping.material
+            ));
+        }
+
+        for &variant in &mapping.variants {
+            if let Some(material_index) = material_indices.get_mut(variant) {
+                *material_index = mapping.material;
+            }
+        }
+    }
+
+    Ok(material_indices)
+}
+
+fn uses_same_pipeline(
+    a: &goth_gltf::Material<Extensions>,
+    b: &goth_gltf::Material<Extensions>,
+) -> bool {
+    let same_alpha_mode = matches!(
+        (a.alpha_mode, b.alpha_mode),
+        (AlphaMode::Opaque, AlphaMode::Opaque)
+            | (AlphaMode::Mask, AlphaMode::Mask)
+            | (AlphaMode::Blend, AlphaMode::Blend)
+    );
+
+    same_alpha_mode && a.double_sided == b.double_sided
+}
+
+// Read the translation, rotation and scale accessors of a node using EXT_mesh_gpu_instancing into
+// a transform per instance. Missing accessors use the identity value.
+fn read_gpu_instance_transforms(
+    translation: Option<usize>,
+    rotation: Option<usize>,
+    scale: Option<usize>,
+    accessor_reader: &AccessorReader,
+) -> anyhow::Result<Vec<Transform>> {
+    let translations = match translation {
+        Some(index) => accessor_reader.read_f32x3(accessor_reader.accessor(index)?)?,
+        None => Vec::new(),
+    };
+
+    let rotations = match rotation {
+        Some(index) => accessor_reader.read_f32x4(accessor_reader.accessor(index)?)?,
+        None => Vec::new(),
+    };
+
+    let scales = match scale {
+        Some(index) => accessor_reader.read_f32x3(accessor_reader.accessor(index)?)?,
+        None => Vec::new(),
+    };
+
+    let num_instances = translations.len().max(rotations.len()).max(scales.len());
+
+    Ok((0..num_instances)
+        .map(|index| {
+            Transform::new_from_gltf(
+                translations.get(index).copied().unwrap_or([0.0; 3]),
+                rotations
+                    .get(index)
+                    .copied()
+                    .unwrap_or([0.0, 0.0, 0.0, 1.0]),
+                scales.get(index).copied().unwrap_or([1.0; 3]),
+            )
+        })
+        .collect())
+}
+
+// Which of a glTF's scenes to load the nodes of.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Scene {
+    // The scene given by the glTF's `scene` property, or the first scene if it isn't set.
+    #[default]
+    Default,
+    Index(usize),
+    Name(String),
+}
+
+// How the scenes of a glTF can be referred to, so that a `Scene` can be resolved to the index of
+// the scene it loads.
+#[derive(Clone, Debug, Default)]
+pub struct SceneIndices {
+    num_scenes: usize,
+    default: usize,
+    names: HashMap<String, usize>,
+}
+
+impl SceneIndices {
+    fn new(gltf: &goth_gltf::Gltf<Extensions>) -> Self {
+        let mut names = HashMap::new();
+
+        // The first scene with a name wins, as with `Iterator::position`.
+        for (index, scene) in gltf.scenes.iter().enumerate() {
+            if let Some(name) = &scene.name {
+                names.entry(name.clone()).or_insert(index);
+            }
+        }
+
+        Self {
+            num_scenes: gltf.scenes.len(),
+            default: gltf.scene.unwrap_or(0),
+            names,
+        }
+    }
+
+    fn index(&self, scene: &Scene) -> anyhow::Result<usize> {
+        match scene {
+            Scene::Default => Ok(self.default),
+            Scene::Index(index) => Ok(*index),
+            Scene::Name(name) => self
+                .names
+                .get(name)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("Model has no scene named '{}'", name)),
+        }
+    }
+
+    // Resolve a scene to the `Scene::Index` that it refers to. glTFs without any scenes load all
+    // their nodes whichever scene is asked for, so those all resolve to `Scene::Default`.
+    pub fn resolve(&self, scene: &Scene) -> Option<Scene> {
+        if self.num_scenes == 0 {
+            return Some(Scene::Default);
+        }
+
+        match self.index(scene) {
+            Ok(index) if index < self.num_scenes => Some(Scene::Index(index)),
+            _ => None,
+        }
+    }
+}
+
+// Collect the indices of all the nodes that are reachable from a scene. glTFs without any scenes
+// have all their nodes loaded.
+fn collect_scene_nodes(
+    gltf: &goth_gltf::Gltf<Extensions>,
+    scene: &Scene,
+) -> anyhow::Result<HashSet<usize>> {
+    if gltf.scenes.is_empty() {
+        return Ok((0..gltf.nodes.len()).collect());
+    }
+
+    let scene_index = SceneIndices::new(gltf).index(scene)?;
+
+    let scene = gltf.scenes.get(scene_index).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Scene index {} is out of range as the model has {} scenes",
+            scene_index,
+            gltf.scenes.len()
+        )
+    })?;
+
+    let mut nodes = HashSet::new();
+    let mut stack = scene.nodes.clone();
+
+    while let Some(node_index) = stack.pop() {
+        if let Some(node) = gltf.nodes.get(node_index) {
+            if nodes.insert(node_index) {
+                stack.extend_from_slice(&node.children);
+            }
+        }
+    }
+
+    Ok(nodes)
+}
+
+// Extensions that a model can list in `extensionsRequired` and still be loaded correctly.
+const SUPPORTED_REQUIRED_EXTENSIONS: &[&str] = &[
+    "EXT_mesh_gpu_instancing",
+    "EXT_meshopt_compression",
+    "KHR_mesh_quantization",
+    "KHR_texture_basisu",
+    "KHR_texture_transform",
+    "KHR_materials_unlit",
+    "KHR_materials_emissive_strength",
+    "MSFT_lod",
+];
+
+fn check_required_extensions(gltf: &goth_gltf::Gltf<Extensions>) -> anyhow::Result<()> {
+    for extension in &gltf.extensions_required {
+        if extension == "KHR_draco_mesh_compression" {
+            return Err(anyhow::anyhow!(
+                "Draco-compressed meshes without an uncompressed fallback are not supported. \
+                Consider re-exporting the model with EXT_meshopt_compression instead."
+            ));
+        }
+
+        if !SUPPORTED_REQUIRED_EXTENSIONS.contains(&extension.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Model requires an unsupported extension: {}",
+                extension
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// The url of the gltf and of every buffer and image that isn't embedded in it.
+fn source_urls(gltf: &goth_gltf::Gltf<Extensions>, root_url: &url::Url) -> Vec<url::Url> {
+    let uris = gltf
+        .buffers
+        .iter()
+        .filter_map(|buffer| buffer.uri.as_ref())
+        .chain(gltf.images.iter().filter_map(|image| image.uri.as_ref()));
+
+    std::iter::once(root_url.clone())
+        .chain(
+            uris.filter_map(|uri| url::Url::options().base_url(Some(root_url)).parse(uri).ok())
+                .filter(|url| url.scheme() != "data"),
+        )
+        .collect()
 }
 
 async fn collect_buffer_view_map<T: HttpClient>(
@@ -162,14 +537,18 @@ async fn collect_buffer_view_map<T: HttpClient>(
     root_url: &url::Url,
     context: &Context<T>,
 ) -> anyhow::Result<Arc<HashMap<usize, Vec<u8>>>> {
-    use std::borrow::Cow;
-
     let mut buffer_map = HashMap::new();
 
+    context
+        .progress
+        .add_expected_buffers(gltf.buffer_views.len() as u32);
+
     if let Some(glb_buffer) = glb_buffer {
         buffer_map.insert(0, Cow::Borrowed(glb_buffer));
     }
 
+    let mut buffers_to_fetch = Vec::new();
+
     for (index, buffer) in gltf.buffers.iter().enumerate() {
         if buffer
             .extensions
@@ -199,19 +578,34 @@ async fn collect_buffer_view_map<T: HttpClient>(
                 Cow::Owned(base64::engine::general_purpose::STANDARD.decode(data)?),
             );
         } else {
-            buffer_map.insert(
-                index,
-                Cow::Owned(context.http_client.fetch_bytes(&url, None).await?),
-            );
+            buffers_to_fetch.push((index, url, buffer.byte_length));
         }
     }
 
+    // Add up all the bytes before fetching anything so that the progress doesn't jump back when
+    // the next buffer starts.
+    context.progress.add_expected_bytes(
+        buffers_to_fetch
+            .iter()
+            .map(|(_, _, byte_length)| *byte_length as u64)
+            .sum(),
+    );
+
+    for (index, url, _) in buffers_to_fetch {
+        let bytes = context
+            .http_client
+            .fetch_bytes_with_progress(&url, None, context.progress.fetch_progress(false))
+            .await?;
+
+        buffer_map.insert(index, Cow::Owned(bytes));
+    }
+
     let mut buffer_view_map = HashMap::new();
 
     for (i, buffer_view) in gltf.buffer_views.iter().enumerate() {
         if let Some(ext) = buffer_view.extensions.ext_meshopt_compression.as_ref() {
             if let Some(buffer) = buffer_map.get(&ext.buffer) {
-                let slice = &buffer[ext.byte_offset..ext.byte_offset + ext.byte_length];
+                let slice = buffer_slice(buffer, ext.byte_offset, ext.byte_length)?;
 
                 let filter = match ext.filter {
                     goth_gltf::extensions::CompressionFilter::None => None,
@@ -229,14 +623,14 @@ async fn collect_buffer_view_map<T: HttpClient>(
                 let bytes: Vec<u8> = match (ext.mode, ext.byte_stride) {
                     (CompressionMode::Triangles, 2) => {
                         meshopt_decoder::TriangleIterator::new(slice, ext.count)
-                            .unwrap()
+                            .map_err(|error| meshopt_error(i, error))?
                             .flatten()
                             .flat_map(|index| (index as u16).to_le_bytes())
                             .collect()
                     }
                     (CompressionMode::Triangles, 4) => {
                         meshopt_decoder::TriangleIterator::new(slice, ext.count)
-                            .unwrap()
+                            .map_err(|error| meshopt_error(i, error))?
                             .flatten()
                             .flat_map(|index| index.to_le_bytes())
                             .collect()
@@ -248,9 +642,15 @@ async fn collect_buffer_view_map<T: HttpClient>(
                             filter,
                             byte_stride,
                         )
-                        .unwrap()
+                        .map_err(|error| meshopt_error(i, error))?
+                    }
+                    (mode, byte_stride) => {
+                        return Err(anyhow::anyhow!(
+                            "Unsupported meshopt compression mode {:?} with a byte stride of {}",
+                            mode,
+                            byte_stride
+                        ))
                     }
-                    x => panic!("{:?}", x),
                 };
 
                 buffer_view_map.insert(i, bytes);
@@ -258,48 +658,108 @@ async fn collect_buffer_view_map<T: HttpClient>(
         } else if let Some(buffer) = buffer_map.get(&buffer_view.buffer) {
             buffer_view_map.insert(
                 i,
-                buffer[buffer_view.byte_offset..buffer_view.byte_offset + buffer_view.byte_length]
-                    .to_vec(),
+                buffer_slice(buffer, buffer_view.byte_offset, buffer_view.byte_length)?.to_vec(),
             );
         }
+
+        context.progress.buffer_decoded();
     }
 
     Ok(Arc::new(buffer_view_map))
 }
 
-#[derive(Debug)]
+fn meshopt_error<E: std::fmt::Debug>(buffer_view: usize, error: E) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Failed to decode meshopt-compressed buffer view {}: {:?}",
+        buffer_view,
+        error
+    )
+}
+
+fn buffer_slice(buffer: &[u8], byte_offset: usize, byte_length: usize) -> anyhow::Result<&[u8]> {
+    byte_offset
+        .checked_add(byte_length)
+        .and_then(|end| buffer.get(byte_offset..end))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Buffer view range {}..{} is out of bounds of a buffer of {} bytes",
+                byte_offset,
+                byte_offset.saturating_add(byte_length),
+                buffer.len()
+            )
+        })
+}
+
 pub struct Model {
     pub primitives: Vec<Primitive>,
     pub primitive_ranges: PrimitiveRanges,
     pub index_buffer_range: Range<u32>,
     pub vertex_buffer_range: Range<u32>,
     pub material_bind_groups: Vec<MaterialBindGroup>,
+    // The cameras and lights in the loaded scene.
+    pub scene_objects: SceneObjects,
+    // The names of the `KHR_materials_variants` variants.
+    pub variants: Vec<String>,
+    // Used to tell which scene the model was loaded from.
+    pub scene_indices: SceneIndices,
+    // The files that the model was loaded from, so that they can be watched for changes.
+    pub source_urls: Vec<url::Url>,
+    index_buffer: Arc<crate::buffers::IndexBuffer>,
+    vertex_buffers: Arc<crate::buffers::VertexBuffers>,
+    // Stops loading the material textures when the model is dropped.
+    _texture_loads: MaterialTextureLoads,
+}
+
+// Hand the model's geometry back to the shared buffers once the last reference is dropped
+// so that the space can be reused by other models.
+impl Drop for Model {
+    fn drop(&mut self) {
+        self.index_buffer.free(self.index_buffer_range.clone());
+        self.vertex_buffers.free(self.vertex_buffer_range.clone());
+    }
 }
 
 impl Model {
     pub async fn load<T: HttpClient>(
         context: &Context<T>,
         root_url: &url::Url,
+        scene: &Scene,
     ) -> anyhow::Result<Self> {
-        let bytes = context.http_client.fetch_bytes(root_url, None).await?;
+        let _permit = context
+            .load_scheduler
+            .acquire(context.load_priority.clone())
+            .await;
+
+        let bytes = context
+            .http_client
+            .fetch_bytes_with_progress(root_url, None, context.progress.fetch_progress(true))
+            .await?;
 
         let (gltf, glb_buffer) = goth_gltf::Gltf::from_bytes(&bytes)?;
+        check_required_extensions(&gltf)?;
+
         let gltf = Arc::new(gltf);
 
         let node_tree = gltf_helpers::NodeTree::new(&gltf);
 
         let buffer_view_map = collect_buffer_view_map(&gltf, glb_buffer, root_url, context).await?;
 
+        let accessor_reader = AccessorReader::new(&gltf, &buffer_view_map);
+
         let mut staging_primitives: permutations::BlendMode<permutations::FaceSides<Vec<_>>> =
             Default::default();
 
-        let material_bind_groups = start_loading_all_material_textures(
+        let (material_bind_groups, texture_loads) = start_loading_all_material_textures(
             &gltf,
             root_url.clone(),
-            context.textures_context(),
+            context,
             buffer_view_map.clone(),
         )?;
 
+        let scene_nodes = collect_scene_nodes(&gltf, scene)?;
+
+        let variants = read_variants(&gltf);
+
         let mut ignored_nodes: HashSet<usize> = HashSet::new();
 
         for node in &gltf.nodes {
@@ -312,13 +772,29 @@ impl Model {
             .nodes
             .iter()
             .enumerate()
-            .filter(|(node_index, _)| !ignored_nodes.contains(node_index))
+            .filter(|(node_index, _)| {
+                scene_nodes.contains(node_index) && !ignored_nodes.contains(node_index)
+            })
             .filter_map(|(node_index, node)| {
                 node.mesh.map(|mesh_index| (node_index, node, mesh_index))
             })
         {
             let transform = node_tree.transform_of(node_index);
 
+            // The instance transforms are relative to the node's transform.
+            let transforms = match &node.extensions.ext_mesh_gpu_instancing {
+                Some(instancing) => read_gpu_instance_transforms(
+                    instancing.attributes.translation,
+                    instancing.attributes.rotation,
+                    instancing.attributes.scale,
+                    &accessor_reader,
+                )?
+                .into_iter()
+                .map(|instance_transform| transform * instance_transform)
+                .collect(),
+                None => vec![transform],
+            };
+
             let mesh = &gltf.meshes[mesh_index];
 
             let mesh_lods = std::iter::once(mesh).chain(
@@ -342,11 +818,18 @@ impl Model {
 
                 for mesh in mesh_lods.clone() {
                     let primitive = &mesh.primitives[primitive_index];
-                    let reader = PrimitiveReader::new(&gltf, primitive, &buffer_view_map);
+
+                    let material_index = primitive.material.unwrap_or(0);
 
                     lods.push(StagingPrimitiveLod {
-                        buffers: StagingBuffers::new(&reader)?,
-                        material_index: primitive.material.unwrap_or(0),
+                        buffers: StagingBuffers::new(primitive, &accessor_reader)?,
+                        material_index,
+                        variant_material_indices: read_variant_material_indices(
+                            primitive,
+                            &gltf.materials,
+                            material_index,
+                            variants.len(),
+                        )?,
                     });
                 }
 
@@ -374,15 +857,18 @@ impl Model {
                     bounding_box: BoundingBox::new(&lods[0].buffers.positions),
                     bounding_sphere: BoundingSphere::new(&lods[0].buffers.positions),
                     lods,
-                    transform,
+                    transforms: transforms.clone(),
                     screen_coverages: node.extras.msft_screencoverage.clone().unwrap_or_default(),
+                    morph_targets: Default::default(),
                 });
             }
         }
 
+        let scene_objects = SceneObjects::new(&gltf, &node_tree, &scene_nodes);
+
         // Collect all the buffers for the primitives into one big staging buffer
         // and collect all the primitive ranges into one big vector.
-        let (mut primitive_ranges, mut primitives, mut staging_buffers) =
+        let (mut primitive_ranges, mut primitives, staging_buffers) =
             collect_all_primitives(&staging_primitives);
 
         let mut command_encoder =
@@ -397,16 +883,12 @@ impl Model {
             &staging_buffers.normals,
             &staging_buffers.uvs,
             &staging_buffers.lightmap_uvs,
+            &staging_buffers.colours,
             &context.device,
             &context.queue,
             &mut command_encoder,
         );
 
-        // Make sure the indices point to the right vertices.
-        for index in &mut staging_buffers.indices {
-            *index += vertex_buffer_range.start;
-        }
-
         let index_buffer_range = context.index_buffer.insert(
             &staging_buffers.indices,
             &context.device,
@@ -441,8 +923,34 @@ impl Model {
             index_buffer_range,
             vertex_buffer_range,
             material_bind_groups,
+            scene_objects,
+            variants,
+            scene_indices: SceneIndices::new(&gltf),
+            source_urls: source_urls(&gltf, root_url),
+            index_buffer: context.index_buffer.clone(),
+            vertex_buffers: context.vertex_buffers.clone(),
+            _texture_loads: texture_loads,
         })
     }
+
+    pub fn variant_index(&self, name: &str) -> Option<usize> {
+        self.variants.iter().position(|variant| variant == name)
+    }
+
+    // Apply the relocations from compacting the index and vertex buffers. The indices are relative
+    // to the start of the model's vertices, so only the ranges need to be updated.
+    pub fn relocate(&mut self, index_relocations: &Relocations, vertex_relocations: &Relocations) {
+        relocate_geometry(
+            RelocatableGeometry {
+                primitives: &mut self.primitives,
+                primitive_ranges: &mut self.primitive_ranges,
+                index_buffer_range: &mut self.index_buffer_range,
+                vertex_buffer_range: &mut self.vertex_buffer_range,
+            },
+            index_relocations,
+            vertex_relocations,
+        );
+    }
 }
 
 pub struct AnimatedModel {
@@ -452,36 +960,94 @@ pub struct AnimatedModel {
     pub vertex_buffer_range: Range<u32>,
     pub animation_data: AnimatedModelData,
     pub material_bind_groups: Vec<MaterialBindGroup>,
+    // Shared with other models when there aren't any morph targets.
+    pub morph_target_texture: Arc<Texture>,
+    // The cameras and lights in the loaded scene. These use the rest pose of the nodes, as
+    // animations aren't applied to them.
+    pub scene_objects: SceneObjects,
+    // The names of the `KHR_materials_variants` variants.
+    pub variants: Vec<String>,
+    // Used to tell which scene the model was loaded from.
+    pub scene_indices: SceneIndices,
+    pub source_urls: Vec<url::Url>,
+    index_buffer: Arc<crate::buffers::IndexBuffer>,
+    vertex_buffers: Arc<crate::buffers::AnimatedVertexBuffers>,
+    _texture_loads: MaterialTextureLoads,
+}
+
+impl Drop for AnimatedModel {
+    fn drop(&mut self) {
+        self.index_buffer.free(self.index_buffer_range.clone());
+        self.vertex_buffers.free(self.vertex_buffer_range.clone());
+    }
 }
 
 impl AnimatedModel {
     pub async fn load<T: HttpClient>(
         context: &Context<T>,
         root_url: &url::Url,
+        scene: &Scene,
     ) -> anyhow::Result<Self> {
-        let bytes = context.http_client.fetch_bytes(root_url, None).await?;
+        let _permit = context
+            .load_scheduler
+            .acquire(context.load_priority.clone())
+            .await;
+
+        let bytes = context
+            .http_client
+            .fetch_bytes_with_progress(root_url, None, context.progress.fetch_progress(true))
+            .await?;
 
         let (gltf, glb_buffer) = goth_gltf::Gltf::from_bytes(&bytes)?;
+        check_required_extensions(&gltf)?;
+
+        // Animated models are drawn once per node, so the instances of EXT_mesh_gpu_instancing
+        // aren't read.
+        if gltf
+            .extensions_required
+            .iter()
+            .any(|extension| extension == "EXT_mesh_gpu_instancing")
+        {
+            return Err(anyhow::anyhow!(
+                "EXT_mesh_gpu_instancing is not supported for animated models"
+            ));
+        }
+
         let gltf = Arc::new(gltf);
 
         let node_tree = gltf_helpers::NodeTree::new(&gltf);
 
         let buffer_view_map = collect_buffer_view_map(&gltf, glb_buffer, root_url, context).await?;
 
+        let accessor_reader = AccessorReader::new(&gltf, &buffer_view_map);
+
         let mut staging_primitives: permutations::BlendMode<permutations::FaceSides<Vec<_>>> =
             Default::default();
 
-        let material_bind_groups = start_loading_all_material_textures(
+        let (material_bind_groups, texture_loads) = start_loading_all_material_textures(
             &gltf,
             root_url.clone(),
-            context.textures_context(),
+            context,
             buffer_view_map.clone(),
         )?;
 
+        let morph_weights = MorphWeights::new(&gltf);
+
+        let mut morph_target_texels = Vec::new();
+
+        let joints = Joints::new(&gltf, &accessor_reader)?;
+
+        let scene_nodes = collect_scene_nodes(&gltf, scene)?;
+
+        let scene_objects = SceneObjects::new(&gltf, &node_tree, &scene_nodes);
+
+        let variants = read_variants(&gltf);
+
         for (node_index, mesh_index) in gltf
             .nodes
             .iter()
             .enumerate()
+            .filter(|(node_index, _)| scene_nodes.contains(node_index))
             .filter_map(|(node_index, node)| node.mesh.map(|mesh_index| (node_index, mesh_index)))
         {
             let mesh = &gltf.meshes[mesh_index];
@@ -508,44 +1074,98 @@ impl AnimatedModel {
                     (AlphaMode::Blend, true) => &mut staging_primitives.alpha_blended.double,
                 };
 
-                let reader = PrimitiveReader::new(&gltf, primitive, &buffer_view_map);
+                let buffers = StagingBuffers::new(primitive, &accessor_reader)?;
+
+                let num_vertices = buffers.positions.len() as u32;
+
+                if primitive.targets.len() != morph_weights.node_weights(node_index).len() {
+                    return Err(anyhow::anyhow!(
+                        "Primitive has {} morph targets but its mesh has {} weights",
+                        primitive.targets.len(),
+                        morph_weights.node_weights(node_index).len()
+                    ));
+                }
 
-                let buffers = StagingBuffers::new(&reader)?;
+                let morph_targets_start = read_morph_targets(
+                    primitive,
+                    num_vertices as usize,
+                    &accessor_reader,
+                    &mut morph_target_texels,
+                )?;
 
                 primitive_vec.push(StagingPrimitive {
                     bounding_box: BoundingBox::new(&buffers.positions),
                     bounding_sphere: BoundingSphere::new(&buffers.positions),
                     lods: vec![StagingPrimitiveLod {
                         buffers: AnimatedStagingBuffers {
-                            joint_indices: match reader.read_joints()? {
-                                Some(joints) => joints.iter().copied().map(UVec4::from).collect(),
-                                None => std::iter::repeat(UVec4::splat(node_index as u32))
-                                    .take(buffers.positions.len())
+                            joint_indices: match (
+                                primitive.attributes.joints_0,
+                                gltf.nodes[node_index].skin,
+                            ) {
+                                (Some(index), Some(_)) => accessor_reader
+                                    .read_u32x4(accessor_reader.accessor(index)?)?
+                                    .into_iter()
+                                    .map(|indices| {
+                                        UVec4::from(indices) + joints.node_offsets[node_index]
+                                    })
                                     .collect(),
-                            },
-                            joint_weights: match reader.read_weights()? {
-                                Some(joint_weights) => {
-                                    joint_weights.iter().copied().map(Vec4::from).collect()
+                                _ => {
+                                    std::iter::repeat(UVec4::splat(joints.node_offsets[node_index]))
+                                        .take(buffers.positions.len())
+                                        .collect()
                                 }
+                            },
+                            joint_weights: match primitive.attributes.weights_0 {
+                                Some(index) => accessor_reader
+                                    .read_f32x4(accessor_reader.accessor(index)?)?
+                                    .into_iter()
+                                    .map(Vec4::from)
+                                    .collect(),
                                 None => std::iter::repeat(Vec4::X)
                                     .take(buffers.positions.len())
                                     .collect(),
                             },
+                            morph_indices: (morph_targets_start
+                                ..morph_targets_start + num_vertices)
+                                .collect(),
                             base: buffers,
                         },
                         material_index,
+                        variant_material_indices: read_variant_material_indices(
+                            primitive,
+                            &gltf.materials,
+                            material_index,
+                            variants.len(),
+                        )?,
                     }],
-                    transform: Similarity::IDENTITY,
+                    transforms: vec![Transform::IDENTITY],
                     screen_coverages: Vec::new(),
+                    morph_targets: PrimitiveMorphTargets {
+                        weights_offset: morph_weights.node_offset(node_index) as u32,
+                        num_targets: primitive.targets.len() as u32,
+                        stride: num_vertices,
+                    },
                 });
             }
         }
 
         // Collect all the buffers for the primitives into one big staging buffer
         // and collect all the primitive ranges into one big vector.
-        let (mut primitive_ranges, mut primitives, mut staging_buffers) =
+        let (mut primitive_ranges, mut primitives, staging_buffers) =
             collect_all_primitives(&staging_primitives);
 
+        // Created before the geometry is uploaded so that a texture that is too large doesn't
+        // leave the geometry allocated.
+        let morph_target_texture = if morph_target_texels.is_empty() {
+            context.empty_morph_target_texture.clone()
+        } else {
+            Arc::new(create_morph_target_texture(
+                &context.device,
+                &context.queue,
+                &morph_target_texels,
+            )?)
+        };
+
         let mut command_encoder =
             context
                 .device
@@ -559,16 +1179,13 @@ impl AnimatedModel {
             &staging_buffers.base.uvs,
             &staging_buffers.joint_indices,
             &staging_buffers.joint_weights,
+            &staging_buffers.base.colours,
+            &staging_buffers.morph_indices,
             &context.device,
             &context.queue,
             &mut command_encoder,
         );
 
-        // Make sure the indices point to the right vertices.
-        for index in &mut staging_buffers.base.indices {
-            *index += vertex_buffer_range.start;
-        }
-
         let index_buffer_range = context.index_buffer.insert(
             &staging_buffers.base.indices,
             &context.device,
@@ -599,60 +1216,27 @@ impl AnimatedModel {
 
         let animations = read_animations(
             &gltf,
-            |accessor| {
-                let (slice, byte_stride) =
-                    read_buffer_with_accessor(&buffer_view_map, &gltf, accessor).unwrap();
-                read_f32(slice, byte_stride, accessor).unwrap()
-            },
-            |accessor| {
-                let (slice, byte_stride) =
-                    read_buffer_with_accessor(&buffer_view_map, &gltf, accessor).unwrap();
-                read_f32x3(slice, byte_stride, accessor).unwrap()
-            },
-            |accessor| {
-                let (slice, byte_stride) =
-                    read_buffer_with_accessor(&buffer_view_map, &gltf, accessor).unwrap();
-                read_f32x4(slice, byte_stride, accessor).unwrap()
-            },
-        );
-
-        if gltf.skins.len() > 1 {
-            log::warn!("Got {} skins. Using the first.", gltf.skins.len());
-        }
-
-        let skin = gltf.skins.first();
-
-        let joint_indices_to_node_indices: Vec<_> = match skin.as_ref() {
-            Some(skin) => skin.joints.clone(),
-            None => (0..gltf.nodes.len()).collect(),
-        };
-
-        let inverse_bind_transforms: Vec<Similarity> = match skin.as_ref() {
-            Some(skin) => {
-                let accessor_index = skin
-                    .inverse_bind_matrices
-                    .ok_or_else(|| anyhow::anyhow!("Missing inverse bind matrices accessor"))?;
-                let accessor = &gltf.accessors[accessor_index];
-
-                let (slice, _byte_stride) =
-                    read_buffer_with_accessor(&buffer_view_map, &gltf, accessor)?;
-
-                let matrices: &[[f32; 16]] = bytemuck::cast_slice(slice);
-
-                matrices
-                    .iter()
-                    .map(|matrix| Similarity::new_from_mat4(Mat4::from_cols_array(matrix)))
-                    .collect()
-            }
-            None => (0..gltf.nodes.len())
-                .map(|_| Similarity::IDENTITY)
-                .collect(),
-        };
+            |accessor| Ok(Cow::Owned(accessor_reader.read_f32(accessor)?)),
+            |accessor| Ok(Cow::Owned(accessor_reader.read_f32x3(accessor)?)),
+            |accessor| Ok(Cow::Owned(accessor_reader.read_f32x4(accessor)?)),
+        )?;
 
         let depth_first_nodes = gltf_helpers::DepthFirstNodes::new(&gltf, &node_tree);
 
         let animation_joints = AnimationJoints::new(&gltf, &depth_first_nodes);
 
+        // The joints can only end up with a non-uniform scale if one of the nodes, inverse bind
+        // matrices or animations has one.
+        let affine_joints = joints
+            .inverse_bind_transforms
+            .iter()
+            .any(Transform::is_affine)
+            || gltf
+                .nodes
+                .iter()
+                .any(|node| Transform::new_from_gltf_node(node).is_affine())
+            || animations.iter().any(Animation::has_non_uniform_scales);
+
         Ok(AnimatedModel {
             primitives,
             primitive_ranges,
@@ -661,20 +1245,109 @@ impl AnimatedModel {
             animation_data: AnimatedModelData {
                 animations,
                 depth_first_nodes,
-                joint_indices_to_node_indices,
-                inverse_bind_transforms,
+                joint_indices_to_node_indices: joints.joint_indices_to_node_indices,
+                inverse_bind_transforms: joints.inverse_bind_transforms,
                 animation_joints,
+                morph_weights,
+                affine_joints,
             },
+            morph_target_texture,
             material_bind_groups,
+            scene_objects,
+            variants,
+            scene_indices: SceneIndices::new(&gltf),
+            source_urls: source_urls(&gltf, root_url),
+            index_buffer: context.index_buffer.clone(),
+            vertex_buffers: context.animated_vertex_buffers.clone(),
+            _texture_loads: texture_loads,
         })
     }
 
+    pub fn variant_index(&self, name: &str) -> Option<usize> {
+        self.variants.iter().position(|variant| variant == name)
+    }
+
+    // See `Model::relocate`.
+    pub fn relocate(&mut self, index_relocations: &Relocations, vertex_relocations: &Relocations) {
+        relocate_geometry(
+            RelocatableGeometry {
+                primitives: &mut self.primitives,
+                primitive_ranges: &mut self.primitive_ranges,
+                index_buffer_range: &mut self.index_buffer_range,
+                vertex_buffer_range: &mut self.vertex_buffer_range,
+            },
+            index_relocations,
+            vertex_relocations,
+        );
+    }
+
     pub fn num_joints(&self) -> u32 {
         self.animation_data.joint_indices_to_node_indices.len() as u32
     }
 
+    pub fn num_morph_weights(&self) -> u32 {
+        self.animation_data.morph_weights.len() as u32
+    }
+
     pub fn max_instances_per_joint_buffer(&self) -> u32 {
-        shared_structs::JointTransform::MAX_COUNT as u32 / self.num_joints()
+        let max_joints = if self.animation_data.affine_joints {
+            shared_structs::MAX_AFFINE_JOINTS
+        } else {
+            shared_structs::MAX_JOINTS
+        };
+
+        let max_instances = max_joints as u32 / self.num_joints();
+
+        match self.num_morph_weights() {
+            0 => max_instances,
+            num_morph_weights => {
+                max_instances.min(shared_structs::MAX_MORPH_WEIGHTS as u32 / num_morph_weights)
+            }
+        }
+    }
+}
+
+struct RelocatableGeometry<'a> {
+    primitives: &'a mut [Primitive],
+    primitive_ranges: &'a mut PrimitiveRanges,
+    index_buffer_range: &'a mut Range<u32>,
+    vertex_buffer_range: &'a mut Range<u32>,
+}
+
+fn relocate_geometry(
+    geometry: RelocatableGeometry,
+    index_relocations: &Relocations,
+    vertex_relocations: &Relocations,
+) {
+    let old_index_start = geometry.index_buffer_range.start;
+
+    *geometry.index_buffer_range =
+        index_relocations.relocate_range(geometry.index_buffer_range.clone());
+    *geometry.vertex_buffer_range =
+        vertex_relocations.relocate_range(geometry.vertex_buffer_range.clone());
+
+    let new_index_start = geometry.index_buffer_range.start;
+
+    if new_index_start != old_index_start {
+        let relocate = |range: &mut Range<u32>| {
+            range.start = range.start - old_index_start + new_index_start;
+            range.end = range.end - old_index_start + new_index_start;
+        };
+
+        for primitive in geometry.primitives.iter_mut() {
+            for lod in &mut primitive.lods {
+                relocate(&mut lod.index_buffer_range);
+            }
+        }
+
+        for range in geometry
+            .primitive_ranges
+            .iter_mut()
+            .iter_mut()
+            .flat_map(|blend_mode| blend_mode.iter_mut())
+        {
+            relocate(&mut range.indices);
+        }
     }
 }
 
@@ -682,13 +1355,15 @@ struct StagingPrimitive<T> {
     lods: Vec<StagingPrimitiveLod<T>>,
     bounding_box: BoundingBox,
     bounding_sphere: BoundingSphere,
-    transform: Similarity,
+    transforms: Vec<Transform>,
     screen_coverages: Vec<f32>,
+    morph_targets: PrimitiveMorphTargets,
 }
 
 struct StagingPrimitiveLod<T> {
     buffers: T,
     material_index: usize,
+    variant_material_indices: Vec<usize>,
 }
 
 #[derive(Debug)]
@@ -696,17 +1371,41 @@ pub struct Primitive {
     pub lods: Vec<PrimitiveLod>,
     pub bounding_box: BoundingBox,
     pub bounding_sphere: BoundingSphere,
-    pub transform: Similarity,
+    // The primitive is drawn once per transform. Nodes using EXT_mesh_gpu_instancing have a
+    // transform per instance.
+    pub transforms: Vec<Transform>,
     pub screen_coverages: Vec<f32>,
+    pub morph_targets: PrimitiveMorphTargets,
+}
+
+// Where to find a primitive's morph targets in the model's morph target texture, and its weights
+// in an instance's morph weights. Each target is stored as `stride` position deltas followed by
+// `stride` normal deltas.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PrimitiveMorphTargets {
+    pub weights_offset: u32,
+    pub num_targets: u32,
+    pub stride: u32,
 }
 
 #[derive(Debug)]
 pub struct PrimitiveLod {
     pub index_buffer_range: Range<u32>,
     pub material_index: usize,
+    // The material used for each of the model's variants. Empty if the model has no variants.
+    pub variant_material_indices: Vec<usize>,
     pub is_lightmapped: bool,
 }
 
+impl PrimitiveLod {
+    pub fn material_index_for_variant(&self, variant: Option<usize>) -> usize {
+        variant
+            .and_then(|variant| self.variant_material_indices.get(variant))
+            .copied()
+            .unwrap_or(self.material_index)
+    }
+}
+
 trait CollectableBuffer {
     fn collect(&mut self, new: &Self) -> Range<u32>;
     fn num_indices(&self) -> u32;
@@ -720,49 +1419,78 @@ struct StagingBuffers {
     normals: Vec<Vec3>,
     uvs: Vec<Vec2>,
     lightmap_uvs: Vec<Vec2>,
+    // Vertex colours are stored as normalized bytes, which is plenty for glTF's 0-1 colours and a
+    // quarter of the size of floats.
+    colours: Vec<[u8; 4]>,
     is_lightmapped: bool,
 }
 
 impl StagingBuffers {
-    fn new(reader: &PrimitiveReader<Extensions>) -> anyhow::Result<Self> {
-        let positions: Vec<Vec3> = reader
-            .read_positions()?
-            .ok_or_else(|| anyhow::anyhow!("Primitive doesn't specifiy vertex positions."))?
-            .iter()
-            .copied()
-            .map(Vec3::from)
-            .collect();
+    fn new(
+        primitive: &goth_gltf::Primitive<Extensions>,
+        accessor_reader: &AccessorReader,
+    ) -> anyhow::Result<Self> {
+        let read_f32x3 = |index: Option<usize>| -> anyhow::Result<Option<Vec<Vec3>>> {
+            index
+                .map(|index| {
+                    let accessor = accessor_reader.accessor(index)?;
+                    let values = accessor_reader.read_f32x3(accessor)?;
+                    Ok(values.into_iter().map(Vec3::from).collect())
+                })
+                .transpose()
+        };
+
+        let read_f32x2 = |index: Option<usize>| -> anyhow::Result<Option<Vec<Vec2>>> {
+            index
+                .map(|index| {
+                    let accessor = accessor_reader.accessor(index)?;
+                    let values = accessor_reader.read_f32x2(accessor)?;
+                    Ok(values.into_iter().map(Vec2::from).collect())
+                })
+                .transpose()
+        };
+
+        let positions = read_f32x3(primitive.attributes.position)?
+            .ok_or_else(|| anyhow::anyhow!("Primitive doesn't specifiy vertex positions."))?;
 
-        let lightmap_uvs = reader.read_second_uvs()?;
+        let lightmap_uvs = read_f32x2(primitive.attributes.texcoord_1)?;
 
         Ok(Self {
-            indices: match reader.read_indices()? {
-                Some(indices) => indices.to_vec(),
+            indices: match primitive.indices {
+                Some(index) => accessor_reader.read_u32(accessor_reader.accessor(index)?)?,
                 None => {
                     log::warn!("No indices specified, using inefficient per-vertex indices.");
 
                     (0..positions.len() as u32).collect()
                 }
             },
-            normals: match reader.read_normals()? {
-                Some(normals) => normals.iter().copied().map(Vec3::from).collect(),
+            normals: match read_f32x3(primitive.attributes.normal)? {
+                Some(normals) => normals,
                 None => std::iter::repeat(Vec3::ZERO)
                     .take(positions.len())
                     .collect(),
             },
-            uvs: match reader.read_uvs()? {
-                Some(uvs) => uvs.iter().copied().map(Vec2::from).collect(),
+            uvs: match read_f32x2(primitive.attributes.texcoord_0)? {
+                Some(uvs) => uvs,
                 None => std::iter::repeat(Vec2::ZERO)
                     .take(positions.len())
                     .collect(),
             },
             is_lightmapped: lightmap_uvs.is_some(),
             lightmap_uvs: match lightmap_uvs {
-                Some(uvs) => uvs.iter().copied().map(Vec2::from).collect(),
+                Some(uvs) => uvs,
                 None => std::iter::repeat(Vec2::ZERO)
                     .take(positions.len())
                     .collect(),
             },
+            colours: match primitive.attributes.color_0 {
+                Some(index) => accessor_reader
+                    .read_colours(accessor_reader.accessor(index)?)?
+                    .into_iter()
+                    .map(|colour| colour.map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8))
+                    .collect(),
+                None => std::iter::repeat([255; 4]).take(positions.len()).collect(),
+            },
             positions,
         })
     }
@@ -780,6 +1508,7 @@ impl CollectableBuffer for StagingBuffers {
         self.normals.extend_from_slice(&new.normals);
         self.uvs.extend_from_slice(&new.uvs);
         self.lightmap_uvs.extend_from_slice(&new.lightmap_uvs);
+        self.colours.extend_from_slice(&new.colours);
 
         let indices_end = self.indices.len() as u32;
 
@@ -800,12 +1529,14 @@ struct AnimatedStagingBuffers {
     base: StagingBuffers,
     joint_indices: Vec<UVec4>,
     joint_weights: Vec<Vec4>,
+    morph_indices: Vec<u32>,
 }
 
 impl CollectableBuffer for AnimatedStagingBuffers {
     fn collect(&mut self, new: &Self) -> Range<u32> {
         self.joint_indices.extend_from_slice(&new.joint_indices);
         self.joint_weights.extend_from_slice(&new.joint_weights);
+        self.morph_indices.extend_from_slice(&new.morph_indices);
 
         self.base.collect(&new.base)
     }
@@ -818,3 +1549,110 @@ impl CollectableBuffer for AnimatedStagingBuffers {
         self.base.is_lightmapped()
     }
 }
+
+// Append the position and normal deltas of a primitive's morph targets to `texels`, returning the
+// index of the first texel of the primitive.
+fn read_morph_targets(
+    primitive: &goth_gltf::Primitive<Extensions>,
+    num_vertices: usize,
+    accessor_reader: &AccessorReader,
+    texels: &mut Vec<Vec4>,
+) -> anyhow::Result<u32> {
+    let start = texels.len() as u32;
+
+    for target in &primitive.targets {
+        for accessor_index in [target.position, target.normal] {
+            match accessor_index {
+                Some(index) => {
+                    let deltas = accessor_reader.read_f32x3(accessor_reader.accessor(index)?)?;
+
+                    if deltas.len() != num_vertices {
+                        return Err(anyhow::anyhow!(
+                            "Morph target has {} deltas for {} vertices",
+                            deltas.len(),
+                            num_vertices
+                        ));
+                    }
+
+                    texels.extend(
+                        deltas
+                            .into_iter()
+                            .map(|delta| Vec3::from(delta).extend(0.0)),
+                    );
+                }
+                None => texels.extend(std::iter::repeat(Vec4::ZERO).take(num_vertices)),
+            }
+        }
+    }
+
+    Ok(start)
+}
+
+fn create_morph_target_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texels: &[Vec4],
+) -> anyhow::Result<Texture> {
+    use wgpu::util::DeviceExt;
+
+    let width = shared_structs::MORPH_TARGET_TEXTURE_WIDTH;
+    let height = (texels.len() as u32).div_ceil(width).max(1);
+
+    let max_height = device.limits().max_texture_dimension_2d;
+
+    if height > max_height {
+        return Err(anyhow::anyhow!(
+            "Morph targets need a {}x{} texture, but the device only supports textures up to {} texels high",
+            width,
+            height,
+            max_height
+        ));
+    }
+
+    let mut padded_texels = texels.to_vec();
+    padded_texels.resize((width * height) as usize, Vec4::ZERO);
+
+    Ok(Texture::new(device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("morph target texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        bytemuck::cast_slice(&padded_texels),
+    )))
+}
+
+// A single texel texture for animated models without any morph targets, so that they don't each
+// need their own.
+pub fn create_empty_morph_target_texture(device: &wgpu::Device, queue: &wgpu::Queue) -> Texture {
+    use wgpu::util::DeviceExt;
+
+    Texture::new(device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("empty morph target texture"),
+            size: wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        bytemuck::cast_slice(&[Vec4::ZERO]),
+    ))
+}
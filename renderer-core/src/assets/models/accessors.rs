@@ -0,0 +1,201 @@
+use gltf_helpers::Extensions;
+use goth_gltf::{Accessor, ComponentType};
+use std::collections::HashMap;
+
+// Reads accessors with any combination of component type, normalization and byte stride.
+//
+// `KHR_mesh_quantization` allows vertex attributes to be stored as (normalized) integers
+// instead of floats. Our vertex buffers only hold floats, so everything gets dequantized here.
+// Non-normalized positions are dequantized by the node transform, which is already applied to
+// each primitive.
+pub(super) struct AccessorReader<'a> {
+    gltf: &'a goth_gltf::Gltf<Extensions>,
+    buffer_view_map: &'a HashMap<usize, Vec<u8>>,
+}
+
+impl<'a> AccessorReader<'a> {
+    pub(super) fn new(
+        gltf: &'a goth_gltf::Gltf<Extensions>,
+        buffer_view_map: &'a HashMap<usize, Vec<u8>>,
+    ) -> Self {
+        Self {
+            gltf,
+            buffer_view_map,
+        }
+    }
+
+    pub(super) fn accessor(&self, index: usize) -> anyhow::Result<&'a Accessor> {
+        self.gltf
+            .accessors
+            .get(index)
+            .ok_or_else(|| anyhow::anyhow!("Accessor index {} out of bounds", index))
+    }
+
+    pub(super) fn read_f32(&self, accessor: &Accessor) -> anyhow::Result<Vec<f32>> {
+        Ok(self
+            .read_elements::<_, 1>(accessor, float_converter(accessor)?)?
+            .into_iter()
+            .map(|[value]| value)
+            .collect())
+    }
+
+    pub(super) fn read_f32x2(&self, accessor: &Accessor) -> anyhow::Result<Vec<[f32; 2]>> {
+        self.read_elements(accessor, float_converter(accessor)?)
+    }
+
+    pub(super) fn read_f32x3(&self, accessor: &Accessor) -> anyhow::Result<Vec<[f32; 3]>> {
+        self.read_elements(accessor, float_converter(accessor)?)
+    }
+
+    pub(super) fn read_f32x4(&self, accessor: &Accessor) -> anyhow::Result<Vec<[f32; 4]>> {
+        self.read_elements(accessor, float_converter(accessor)?)
+    }
+
+    pub(super) fn read_f32x16(&self, accessor: &Accessor) -> anyhow::Result<Vec<[f32; 16]>> {
+        self.read_elements(accessor, float_converter(accessor)?)
+    }
+
+    // Vertex colours can be either RGB or RGBA.
+    pub(super) fn read_colours(&self, accessor: &Accessor) -> anyhow::Result<Vec<[f32; 4]>> {
+        match accessor.accessor_type.num_components() {
+            3 => Ok(self
+                .read_f32x3(accessor)?
+                .into_iter()
+                .map(|[r, g, b]| [r, g, b, 1.0])
+                .collect()),
+            _ => self.read_f32x4(accessor),
+        }
+    }
+
+    pub(super) fn read_u32(&self, accessor: &Accessor) -> anyhow::Result<Vec<u32>> {
+        Ok(self
+            .read_elements::<_, 1>(accessor, integer_converter(accessor)?)?
+            .into_iter()
+            .map(|[value]| value)
+            .collect())
+    }
+
+    pub(super) fn read_u32x4(&self, accessor: &Accessor) -> anyhow::Result<Vec<[u32; 4]>> {
+        self.read_elements(accessor, integer_converter(accessor)?)
+    }
+
+    fn read_elements<T: Copy + Default, const N: usize>(
+        &self,
+        accessor: &Accessor,
+        convert: fn(&[u8]) -> T,
+    ) -> anyhow::Result<Vec<[T; N]>> {
+        let num_components = accessor.accessor_type.num_components();
+
+        if num_components != N {
+            return Err(anyhow::anyhow!(
+                "Expected an accessor with {} components, got {:?}",
+                N,
+                accessor.accessor_type
+            ));
+        }
+
+        // Accessors without a buffer view are all zeros.
+        let buffer_view_index = match accessor.buffer_view {
+            Some(index) => index,
+            None => return Ok(vec![[T::default(); N]; accessor.count]),
+        };
+
+        let buffer_view = self
+            .gltf
+            .buffer_views
+            .get(buffer_view_index)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Buffer view index {} out of bounds", buffer_view_index)
+            })?;
+
+        let bytes = self
+            .buffer_view_map
+            .get(&buffer_view_index)
+            .ok_or_else(|| anyhow::anyhow!("Buffer view {} wasn't loaded", buffer_view_index))?;
+
+        let component_size = accessor.component_type.byte_size();
+        let element_size = component_size * N;
+
+        // Meshopt-compressed buffer views are decoded with the stride from the extension.
+        let byte_stride = buffer_view
+            .extensions
+            .ext_meshopt_compression
+            .as_ref()
+            .map(|ext| ext.byte_stride)
+            .or(buffer_view.byte_stride)
+            .unwrap_or(element_size);
+
+        if accessor.count > 0 {
+            let end = accessor.byte_offset + (accessor.count - 1) * byte_stride + element_size;
+
+            if end > bytes.len() {
+                return Err(anyhow::anyhow!(
+                    "Accessor reads up to byte {} of buffer view {}, which is only {} bytes long",
+                    end,
+                    buffer_view_index,
+                    bytes.len()
+                ));
+            }
+        }
+
+        Ok((0..accessor.count)
+            .map(|index| {
+                let offset = accessor.byte_offset + index * byte_stride;
+
+                std::array::from_fn(|component| {
+                    convert(&bytes[offset + component * component_size..])
+                })
+            })
+            .collect())
+    }
+}
+
+fn float_converter(accessor: &Accessor) -> anyhow::Result<fn(&[u8]) -> f32> {
+    let converter: fn(&[u8]) -> f32 = match (accessor.component_type, accessor.normalized) {
+        (ComponentType::Float, false) => {
+            |bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        }
+        (ComponentType::Byte, true) => |bytes| (bytes[0] as i8 as f32 / 127.0).max(-1.0),
+        (ComponentType::Byte, false) => |bytes| bytes[0] as i8 as f32,
+        (ComponentType::UnsignedByte, true) => |bytes| bytes[0] as f32 / 255.0,
+        (ComponentType::UnsignedByte, false) => |bytes| bytes[0] as f32,
+        (ComponentType::Short, true) => {
+            |bytes| (i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32767.0).max(-1.0)
+        }
+        (ComponentType::Short, false) => |bytes| i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+        (ComponentType::UnsignedShort, true) => {
+            |bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 65535.0
+        }
+        (ComponentType::UnsignedShort, false) => {
+            |bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as f32
+        }
+        other => {
+            return Err(anyhow::anyhow!(
+                "Unsupported component type and normalization for a float accessor: {:?}",
+                other
+            ))
+        }
+    };
+
+    Ok(converter)
+}
+
+fn integer_converter(accessor: &Accessor) -> anyhow::Result<fn(&[u8]) -> u32> {
+    let converter: fn(&[u8]) -> u32 = match (accessor.component_type, accessor.normalized) {
+        (ComponentType::UnsignedByte, false) => |bytes| bytes[0] as u32,
+        (ComponentType::UnsignedShort, false) => {
+            |bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as u32
+        }
+        (ComponentType::UnsignedInt, false) => {
+            |bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        }
+        other => {
+            return Err(anyhow::anyhow!(
+                "Unsupported component type and normalization for an integer accessor: {:?}",
+                other
+            ))
+        }
+    };
+
+    Ok(converter)
+}
@@ -0,0 +1,128 @@
+use crate::assets::{FetchProgress, FetchProgressCallback};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+// Counters that get updated as a model loads. These are shared between the loading futures and
+// whatever wants to display the progress, so everything is atomic.
+#[derive(Default, Debug)]
+pub struct LoadProgress {
+    bytes_fetched: AtomicU64,
+    bytes_expected: AtomicU64,
+    buffers_decoded: AtomicU32,
+    buffers_expected: AtomicU32,
+    textures_loaded: AtomicU32,
+    textures_expected: AtomicU32,
+    mip_levels_uploaded: AtomicU32,
+    mip_levels_expected: AtomicU32,
+}
+
+impl LoadProgress {
+    pub(super) fn add_expected_bytes(&self, bytes: u64) {
+        self.bytes_expected.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(super) fn add_fetched_bytes(&self, bytes: u64) {
+        self.bytes_fetched.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    // Count the bytes of a fetch as they arrive. `count_content_length` should be false if the
+    // expected bytes have already been added, such as for buffers with a `byteLength`.
+    pub(super) fn fetch_progress(
+        self: &Arc<Self>,
+        count_content_length: bool,
+    ) -> FetchProgressCallback {
+        let progress = self.clone();
+
+        Arc::new(move |event| match event {
+            FetchProgress::ContentLength(bytes) => {
+                if count_content_length {
+                    progress.add_expected_bytes(bytes);
+                }
+            }
+            FetchProgress::Chunk(bytes) => progress.add_fetched_bytes(bytes),
+        })
+    }
+
+    pub(super) fn add_expected_buffers(&self, buffers: u32) {
+        self.buffers_expected.fetch_add(buffers, Ordering::Relaxed);
+    }
+
+    pub(super) fn buffer_decoded(&self) {
+        self.buffers_decoded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn add_expected_texture(&self) {
+        self.textures_expected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Called once a texture has finished loading, even if it failed and the material kept the
+    // fallback texture, so that the progress can still reach completion.
+    pub(super) fn texture_loaded(&self) {
+        self.textures_loaded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn add_expected_mip_levels(&self, levels: u32) {
+        self.mip_levels_expected
+            .fetch_add(levels, Ordering::Relaxed);
+    }
+
+    pub(super) fn mip_level_uploaded(&self) {
+        self.mip_levels_uploaded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> LoadProgressSnapshot {
+        LoadProgressSnapshot {
+            bytes_fetched: self.bytes_fetched.load(Ordering::Relaxed),
+            bytes_expected: self.bytes_expected.load(Ordering::Relaxed),
+            buffers_decoded: self.buffers_decoded.load(Ordering::Relaxed),
+            buffers_expected: self.buffers_expected.load(Ordering::Relaxed),
+            textures_loaded: self.textures_loaded.load(Ordering::Relaxed),
+            textures_expected: self.textures_expected.load(Ordering::Relaxed),
+            mip_levels_uploaded: self.mip_levels_uploaded.load(Ordering::Relaxed),
+            mip_levels_expected: self.mip_levels_expected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Expected counts only grow as more of the model is parsed, so the bytes expected only cover the
+// gltf and the buffers it references, not textures. Only KTX2 textures count their mip levels, and
+// their expected levels are added once each header has been fetched.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct LoadProgressSnapshot {
+    pub bytes_fetched: u64,
+    pub bytes_expected: u64,
+    pub buffers_decoded: u32,
+    pub buffers_expected: u32,
+    pub textures_loaded: u32,
+    pub textures_expected: u32,
+    pub mip_levels_uploaded: u32,
+    pub mip_levels_expected: u32,
+}
+
+impl LoadProgressSnapshot {
+    // A rough 0-1 value for progress bars, averaging the stages that have started.
+    pub fn fraction(&self) -> f32 {
+        let stages = [
+            (self.bytes_fetched as f64, self.bytes_expected as f64),
+            (self.buffers_decoded as f64, self.buffers_expected as f64),
+            (self.textures_loaded as f64, self.textures_expected as f64),
+            (
+                self.mip_levels_uploaded as f64,
+                self.mip_levels_expected as f64,
+            ),
+        ];
+
+        let (sum, count) = stages
+            .iter()
+            .filter(|(_, expected)| *expected > 0.0)
+            .fold((0.0, 0), |(sum, count), (done, expected)| {
+                (sum + (done / expected).min(1.0), count + 1)
+            });
+
+        if count == 0 {
+            0.0
+        } else {
+            (sum / count as f64) as f32
+        }
+    }
+}
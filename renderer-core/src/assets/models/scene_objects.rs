@@ -0,0 +1,148 @@
+use gltf_helpers::{Extensions, NodeTree, Transform};
+use goth_gltf::extensions::LightType;
+use std::collections::HashSet;
+
+// Cameras and `KHR_lights_punctual` lights attached to the nodes of a model. Transforms are
+// relative to the origin of the model.
+
+#[derive(Clone, Debug)]
+pub struct Camera {
+    pub name: Option<String>,
+    pub transform: Transform,
+    pub projection: Projection,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Projection {
+    Perspective {
+        // The vertical field of view, in radians.
+        y_fov: f32,
+        // Cameras without an aspect ratio should use the aspect ratio of the viewport.
+        aspect_ratio: Option<f32>,
+        z_near: f32,
+        // Infinite projections don't have a far plane.
+        z_far: Option<f32>,
+    },
+    Orthographic {
+        x_mag: f32,
+        y_mag: f32,
+        z_near: f32,
+        z_far: f32,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Light {
+    pub name: Option<String>,
+    pub transform: Transform,
+    pub kind: LightKind,
+    // Linear RGB.
+    pub colour: [f32; 3],
+    // Lux for directional lights, candela for point and spot lights.
+    pub intensity: f32,
+    // The distance at which the light's intensity reaches zero. Unset for infinite range.
+    pub range: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot {
+        // Both in radians.
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SceneObjects {
+    pub cameras: Vec<Camera>,
+    pub lights: Vec<Light>,
+}
+
+impl SceneObjects {
+    pub(super) fn new(
+        gltf: &goth_gltf::Gltf<Extensions>,
+        node_tree: &NodeTree,
+        scene_nodes: &HashSet<usize>,
+    ) -> Self {
+        let mut scene_objects = Self::default();
+
+        let lights = gltf
+            .extensions
+            .khr_lights_punctual
+            .as_ref()
+            .map(|extension| &extension.lights[..])
+            .unwrap_or(&[]);
+
+        for (node_index, node) in gltf
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(node_index, _)| scene_nodes.contains(node_index))
+        {
+            if let Some(camera) = node.camera.and_then(|index| gltf.cameras.get(index)) {
+                let projection = match (&camera.perspective, &camera.orthographic) {
+                    (Some(perspective), _) => Some(Projection::Perspective {
+                        y_fov: perspective.yfov,
+                        aspect_ratio: perspective.aspect_ratio,
+                        z_near: perspective.znear,
+                        z_far: perspective.zfar,
+                    }),
+                    (None, Some(orthographic)) => Some(Projection::Orthographic {
+                        x_mag: orthographic.xmag,
+                        y_mag: orthographic.ymag,
+                        z_near: orthographic.znear,
+                        z_far: orthographic.zfar,
+                    }),
+                    (None, None) => {
+                        log::warn!("Camera on node {} has no projection", node_index);
+                        None
+                    }
+                };
+
+                if let Some(projection) = projection {
+                    scene_objects.cameras.push(Camera {
+                        name: camera.name.clone().or_else(|| node.name.clone()),
+                        transform: node_tree.transform_of(node_index),
+                        projection,
+                    });
+                }
+            }
+
+            if let Some(light) = node
+                .extensions
+                .khr_lights_punctual
+                .as_ref()
+                .and_then(|extension| lights.get(extension.light))
+            {
+                scene_objects.lights.push(Light {
+                    name: light.name.clone().or_else(|| node.name.clone()),
+                    transform: node_tree.transform_of(node_index),
+                    kind: match light.type_ {
+                        LightType::Directional => LightKind::Directional,
+                        LightType::Point => LightKind::Point,
+                        LightType::Spot => {
+                            let spot = light.spot.as_ref();
+
+                            LightKind::Spot {
+                                inner_cone_angle: spot
+                                    .map(|spot| spot.inner_cone_angle)
+                                    .unwrap_or(0.0),
+                                outer_cone_angle: spot
+                                    .map(|spot| spot.outer_cone_angle)
+                                    .unwrap_or(std::f32::consts::FRAC_PI_4),
+                            }
+                        }
+                    },
+                    colour: light.color,
+                    intensity: light.intensity,
+                    range: light.range,
+                });
+            }
+        }
+
+        scene_objects
+    }
+}
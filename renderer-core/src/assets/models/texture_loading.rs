@@ -1,14 +1,15 @@
 #[cfg(feature = "wasm")]
-pub type PendingTexture =
-    future::Shared<std::pin::Pin<Box<dyn Future<Output = Option<Arc<Texture>>> + 'static>>>;
+type TextureFuture = std::pin::Pin<Box<dyn Future<Output = Option<Arc<Texture>>> + 'static>>;
 
 #[cfg(not(feature = "wasm"))]
-pub type PendingTexture =
-    future::Shared<std::pin::Pin<Box<dyn Future<Output = Option<Arc<Texture>>> + Send + 'static>>>;
+type TextureFuture = std::pin::Pin<Box<dyn Future<Output = Option<Arc<Texture>>> + Send + 'static>>;
 
-use crate::assets::textures::{self, load_image_with_mime_type, ImageSource};
+pub type PendingTexture = future::Shared<TextureFuture>;
+
+use super::Context;
+use crate::assets::textures::{load_image_with_mime_type, ImageSource, MipLevelProgress};
 use crate::assets::HttpClient;
-use crate::{spawn, Texture};
+use crate::{spawn_abortable, Texture};
 use base64::Engine;
 use futures::future::{self, FutureExt, OptionFuture};
 use glam::{Vec2, Vec3};
@@ -20,6 +21,116 @@ use wgpu::util::DeviceExt;
 
 pub type MaterialBindGroup = Arc<crate::MutableBindGroup>;
 
+// Aborts the texture loading for a model's materials when dropped, either because the model was
+// dropped or because the model's loading was cancelled.
+pub(super) struct MaterialTextureLoads(Vec<future::AbortHandle>);
+
+impl Drop for MaterialTextureLoads {
+    fn drop(&mut self) {
+        for abort_handle in &self.0 {
+            abort_handle.abort();
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+fn into_pending_texture<F: Future<Output = Option<Arc<Texture>>> + 'static>(
+    future: F,
+) -> PendingTexture {
+    future.boxed_local().shared()
+}
+
+#[cfg(not(feature = "wasm"))]
+fn into_pending_texture<F: Future<Output = Option<Arc<Texture>>> + Send + 'static>(
+    future: F,
+) -> PendingTexture {
+    future.boxed().shared()
+}
+
+// Textures loaded from urls, shared between all models that use them. The cache only keeps weak
+// references, so textures get freed when the last material using them is dropped, and loads stop
+// once nothing is waiting on them.
+#[derive(Clone, Default)]
+pub struct TextureCache {
+    entries: Arc<parking_lot::Mutex<HashMap<(url::Url, bool), TextureCacheEntry>>>,
+}
+
+// Safety: futures aren't `Send` on wasm, but there's only a single thread there anyway.
+#[cfg(feature = "wasm")]
+unsafe impl Send for TextureCache {}
+#[cfg(feature = "wasm")]
+unsafe impl Sync for TextureCache {}
+
+enum TextureCacheEntry {
+    Pending(future::WeakShared<TextureFuture>),
+    Loaded(std::sync::Weak<Texture>),
+}
+
+impl TextureCache {
+    // Forget the textures loaded from a url, so that they get loaded again the next time they're
+    // used. Models that are already using them keep the old textures.
+    pub fn remove(&self, url: &url::Url) {
+        self.entries
+            .lock()
+            .retain(|(entry_url, _), _| entry_url != url);
+    }
+
+    fn get(&self, url: &url::Url, srgb: bool) -> Option<PendingTexture> {
+        let mut entries = self.entries.lock();
+
+        let key = (url.clone(), srgb);
+
+        let pending = match entries.get(&key)? {
+            TextureCacheEntry::Pending(future) => future.upgrade(),
+            TextureCacheEntry::Loaded(texture) => texture
+                .upgrade()
+                .map(|texture| into_pending_texture(future::ready(Some(texture)))),
+        };
+
+        if pending.is_none() {
+            entries.remove(&key);
+        }
+
+        pending
+    }
+
+    fn insert(&self, url: url::Url, srgb: bool, future: PendingTexture) -> PendingTexture {
+        let entries = self.entries.clone();
+        let key = (url, srgb);
+
+        let future = into_pending_texture(future.map({
+            let key = key.clone();
+
+            move |texture| {
+                let mut entries = entries.lock();
+
+                match &texture {
+                    Some(texture) => {
+                        entries.insert(key, TextureCacheEntry::Loaded(Arc::downgrade(texture)));
+                    }
+                    None => {
+                        entries.remove(&key);
+                    }
+                }
+
+                texture
+            }
+        }));
+
+        if let Some(weak_future) = future.downgrade() {
+            self.entries
+                .lock()
+                .insert(key, TextureCacheEntry::Pending(weak_future));
+        }
+
+        future
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+}
+
 pub fn image_index_from_texture_index(
     texture_index: usize,
     gltf: &goth_gltf::Gltf<Extensions>,
@@ -76,11 +187,14 @@ fn load_single_pixel_image(
 pub fn start_loading_all_material_textures<T: HttpClient>(
     gltf: &goth_gltf::Gltf<Extensions>,
     root_url: url::Url,
-    textures_context: textures::Context<T>,
+    context: &Context<T>,
     buffer_view_map: Arc<HashMap<usize, Vec<u8>>>,
-) -> anyhow::Result<Vec<MaterialBindGroup>> {
+) -> anyhow::Result<(Vec<MaterialBindGroup>, MaterialTextureLoads)> {
+    let textures_context = context.textures_context();
     let mut pending_textures = Default::default();
+    let mut samplers: HashMap<Option<usize>, Arc<wgpu::Sampler>> = HashMap::new();
     let mut materials = Vec::new();
+    let mut abort_handles = Vec::new();
 
     for material in &gltf.materials {
         let albedo_future =
@@ -91,7 +205,7 @@ pub fn start_loading_all_material_textures<T: HttpClient>(
                     gltf,
                     &mut pending_textures,
                     root_url.clone(),
-                    textures_context.clone(),
+                    context,
                     buffer_view_map.clone(),
                 )?)
             } else {
@@ -109,7 +223,7 @@ pub fn start_loading_all_material_textures<T: HttpClient>(
                 gltf,
                 &mut pending_textures,
                 root_url.clone(),
-                textures_context.clone(),
+                context,
                 buffer_view_map.clone(),
             )?)
         } else {
@@ -123,7 +237,7 @@ pub fn start_loading_all_material_textures<T: HttpClient>(
                 gltf,
                 &mut pending_textures,
                 root_url.clone(),
-                textures_context.clone(),
+                context,
                 buffer_view_map.clone(),
             )?)
         } else {
@@ -137,25 +251,54 @@ pub fn start_loading_all_material_textures<T: HttpClient>(
                 gltf,
                 &mut pending_textures,
                 root_url.clone(),
-                textures_context.clone(),
+                context,
                 buffer_view_map.clone(),
             )?)
         } else {
             None
         };
 
-        let linear_sampler = Arc::new(textures_context.device.create_sampler(
-            &wgpu::SamplerDescriptor {
-                address_mode_u: wgpu::AddressMode::Repeat,
-                address_mode_v: wgpu::AddressMode::Repeat,
-                address_mode_w: wgpu::AddressMode::Repeat,
-                mag_filter: wgpu::FilterMode::Linear,
-                min_filter: wgpu::FilterMode::Linear,
-                mipmap_filter: wgpu::FilterMode::Linear,
-                anisotropy_clamp: textures_context.settings.anisotropy_clamp,
-                ..Default::default()
-            },
-        ));
+        let occlusion_future = if let Some(tex_info) = material.occlusion_texture.as_ref() {
+            Some(start_loading_texture(
+                tex_info.index,
+                false,
+                gltf,
+                &mut pending_textures,
+                root_url.clone(),
+                context,
+                buffer_view_map.clone(),
+            )?)
+        } else {
+            None
+        };
+
+        let mut sampler = |texture_index: Option<usize>| {
+            let sampler_index = texture_sampler_index(texture_index, gltf);
+
+            crate::mutable_bind_group::Entry::Sampler(
+                samplers
+                    .entry(sampler_index)
+                    .or_insert_with(|| {
+                        Arc::new(create_sampler(
+                            sampler_index.and_then(|index| gltf.samplers.get(index)),
+                            textures_context.settings.anisotropy_clamp,
+                            &textures_context.device,
+                        ))
+                    })
+                    .clone(),
+            )
+        };
+
+        let pbr = &material.pbr_metallic_roughness;
+        let albedo_sampler = sampler(pbr.base_color_texture.as_ref().map(|info| info.index));
+        let normal_sampler = sampler(material.normal_texture.as_ref().map(|info| info.index));
+        let metallic_roughness_sampler = sampler(
+            pbr.metallic_roughness_texture
+                .as_ref()
+                .map(|info| info.index),
+        );
+        let emissive_sampler = sampler(material.emissive_texture.as_ref().map(|info| info.index));
+        let occlusion_sampler = sampler(material.occlusion_texture.as_ref().map(|info| info.index));
 
         let material_settings = load_material_settings(material);
 
@@ -201,7 +344,17 @@ pub fn start_loading_all_material_textures<T: HttpClient>(
                     )),
                     0,
                 ),
-                crate::mutable_bind_group::Entry::Sampler(linear_sampler),
+                albedo_sampler,
+                crate::mutable_bind_group::Entry::Texture(load_single_pixel_image(
+                    &textures_context.device,
+                    &textures_context.queue,
+                    wgpu::TextureFormat::Rgba8Unorm,
+                    &[255, 255, 255, 255],
+                )),
+                normal_sampler,
+                metallic_roughness_sampler,
+                emissive_sampler,
+                occlusion_sampler,
             ],
         ));
 
@@ -210,15 +363,21 @@ pub fn start_loading_all_material_textures<T: HttpClient>(
         let device = textures_context.device.clone();
         let bind_group_layouts = textures_context.bind_group_layouts.clone();
 
-        spawn(async move {
-            let (albedo_texture, metallic_roughness_texture, normal_texture, emissive_texture) =
-                futures::future::join4(
-                    OptionFuture::from(albedo_future).map(|option| option.flatten()),
-                    OptionFuture::from(metallic_roughness_future).map(|option| option.flatten()),
-                    OptionFuture::from(normal_future).map(|option| option.flatten()),
-                    OptionFuture::from(emissive_future).map(|option| option.flatten()),
-                )
-                .await;
+        let future = async move {
+            let (
+                albedo_texture,
+                metallic_roughness_texture,
+                normal_texture,
+                emissive_texture,
+                occlusion_texture,
+            ) = futures::future::join5(
+                OptionFuture::from(albedo_future).map(|option| option.flatten()),
+                OptionFuture::from(metallic_roughness_future).map(|option| option.flatten()),
+                OptionFuture::from(normal_future).map(|option| option.flatten()),
+                OptionFuture::from(emissive_future).map(|option| option.flatten()),
+                OptionFuture::from(occlusion_future).map(|option| option.flatten()),
+            )
+            .await;
 
             bind_group.mutate(&device, &bind_group_layouts.model, |entries| {
                 if let Some(albedo_texture) = albedo_texture {
@@ -237,13 +396,22 @@ pub fn start_loading_all_material_textures<T: HttpClient>(
                 if let Some(emissive_texture) = emissive_texture {
                     entries[3] = crate::mutable_bind_group::Entry::Texture(emissive_texture);
                 }
+
+                if let Some(occlusion_texture) = occlusion_texture {
+                    entries[6] = crate::mutable_bind_group::Entry::Texture(occlusion_texture);
+                }
             });
 
             Ok(())
-        });
+        };
+
+        let (abort_handle, abort_registration) = future::AbortHandle::new_pair();
+        abort_handles.push(abort_handle);
+
+        spawn_abortable(future, abort_registration);
     }
 
-    Ok(materials)
+    Ok((materials, MaterialTextureLoads(abort_handles)))
 }
 
 fn start_loading_texture<T: HttpClient>(
@@ -252,7 +420,7 @@ fn start_loading_texture<T: HttpClient>(
     gltf: &goth_gltf::Gltf<Extensions>,
     pending_textures: &mut HashMap<usize, PendingTexture>,
     root_url: url::Url,
-    textures_context: textures::Context<T>,
+    context: &Context<T>,
     buffer_view_map: Arc<HashMap<usize, Vec<u8>>>,
 ) -> anyhow::Result<PendingTexture> {
     let image_index = image_index_from_texture_index(texture_index, gltf)?;
@@ -272,7 +440,59 @@ fn start_loading_texture<T: HttpClient>(
         }
     };
 
+    let progress = &context.progress;
+    let texture_cache = &context.texture_cache;
+
+    progress.add_expected_texture();
+
+    // Images that aren't embedded in the gltf can be shared with other models.
+    let cached_url = image
+        .uri
+        .as_ref()
+        .and_then(|uri| {
+            url::Url::options()
+                .base_url(Some(&root_url))
+                .parse(uri)
+                .ok()
+        })
+        .filter(|url| url.scheme() != "data");
+
+    let count_texture_loaded = {
+        let progress = progress.clone();
+
+        move |texture: Option<Arc<Texture>>| {
+            progress.texture_loaded();
+            texture
+        }
+    };
+
+    if let Some(future) = cached_url
+        .as_ref()
+        .and_then(|url| texture_cache.get(url, srgb))
+    {
+        let future = into_pending_texture(future.map(count_texture_loaded));
+
+        pending_textures.insert(image_index, future.clone());
+
+        return Ok(future);
+    }
+
+    let on_level_load = {
+        let progress = progress.clone();
+        move |level_progress| match level_progress {
+            MipLevelProgress::Expected(levels) => progress.add_expected_mip_levels(levels),
+            MipLevelProgress::Uploaded(_) => progress.mip_level_uploaded(),
+        }
+    };
+
+    let textures_context = context.textures_context();
+    let acquire_permit = context
+        .load_scheduler
+        .acquire(context.load_priority.clone());
+
     let future = async move {
+        let _permit = acquire_permit.await;
+
         if let Some(uri) = &image.uri {
             let url = url::Url::options().base_url(Some(&root_url)).parse(uri)?;
 
@@ -289,6 +509,7 @@ fn start_loading_texture<T: HttpClient>(
                     srgb,
                     image.mime_type.as_ref().map(|string| &string[..]),
                     &textures_context,
+                    on_level_load,
                 )
                 .await
             } else {
@@ -297,6 +518,7 @@ fn start_loading_texture<T: HttpClient>(
                     srgb,
                     image.mime_type.as_ref().map(|string| &string[..]),
                     &textures_context,
+                    on_level_load,
                 )
                 .await
             }
@@ -307,6 +529,7 @@ fn start_loading_texture<T: HttpClient>(
                 srgb,
                 image.mime_type.as_ref().map(|string| &string[..]),
                 &textures_context,
+                on_level_load,
             )
             .await
         } else {
@@ -316,25 +539,102 @@ fn start_loading_texture<T: HttpClient>(
         }
     };
 
-    let future = future.map(|result| match result {
+    let future = into_pending_texture(future.map(|result| match result {
         Ok(texture) => Some(texture),
         Err(error) => {
             log::error!("{}", error);
             None
         }
-    });
+    }));
 
-    #[cfg(feature = "wasm")]
-    let future = future.boxed_local().shared();
+    let future = match cached_url {
+        Some(url) => texture_cache.insert(url, srgb, future),
+        None => future,
+    };
 
-    #[cfg(not(feature = "wasm"))]
-    let future = future.boxed().shared();
+    let future = into_pending_texture(future.map(count_texture_loaded));
 
     pending_textures.insert(image_index, future.clone());
 
     Ok(future)
 }
 
+// Each texture of a material gets its own sampler. Samplers are shared between the textures that
+// use the same glTF sampler.
+fn texture_sampler_index(
+    texture_index: Option<usize>,
+    gltf: &goth_gltf::Gltf<Extensions>,
+) -> Option<usize> {
+    texture_index
+        .and_then(|texture_index| gltf.textures.get(texture_index))
+        .and_then(|texture| texture.sampler)
+}
+
+// Textures without a sampler use repeat wrapping and the highest quality filtering.
+fn create_sampler(
+    sampler: Option<&goth_gltf::Sampler>,
+    anisotropy_clamp: u16,
+    device: &wgpu::Device,
+) -> wgpu::Sampler {
+    use goth_gltf::{FilterMode, MinFilter, SamplerWrap};
+
+    let address_mode = |wrap: SamplerWrap| match wrap {
+        SamplerWrap::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        SamplerWrap::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        SamplerWrap::Repeat => wgpu::AddressMode::Repeat,
+    };
+
+    let mag_filter = match sampler.and_then(|sampler| sampler.mag_filter) {
+        Some(FilterMode::Nearest) => wgpu::FilterMode::Nearest,
+        Some(FilterMode::Linear) | None => wgpu::FilterMode::Linear,
+    };
+
+    // The non-mipmapped filters only sample the base level.
+    let (min_filter, mipmap_filter, lod_max_clamp) =
+        match sampler.and_then(|sampler| sampler.min_filter) {
+            Some(MinFilter::Nearest) => (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest, 0.0),
+            Some(MinFilter::Linear) => (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest, 0.0),
+            Some(MinFilter::NearestMipmapNearest) => {
+                (wgpu::FilterMode::Nearest, wgpu::FilterMode::Nearest, 32.0)
+            }
+            Some(MinFilter::LinearMipmapNearest) => {
+                (wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest, 32.0)
+            }
+            Some(MinFilter::NearestMipmapLinear) => {
+                (wgpu::FilterMode::Nearest, wgpu::FilterMode::Linear, 32.0)
+            }
+            Some(MinFilter::LinearMipmapLinear) | None => {
+                (wgpu::FilterMode::Linear, wgpu::FilterMode::Linear, 32.0)
+            }
+        };
+
+    // Anisotropic filtering is only valid when all the filters are linear.
+    let anisotropy_clamp = if mag_filter == wgpu::FilterMode::Linear
+        && min_filter == wgpu::FilterMode::Linear
+        && mipmap_filter == wgpu::FilterMode::Linear
+    {
+        anisotropy_clamp
+    } else {
+        1
+    };
+
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: sampler
+            .map(|sampler| address_mode(sampler.wrap_s))
+            .unwrap_or(wgpu::AddressMode::Repeat),
+        address_mode_v: sampler
+            .map(|sampler| address_mode(sampler.wrap_t))
+            .unwrap_or(wgpu::AddressMode::Repeat),
+        address_mode_w: wgpu::AddressMode::Repeat,
+        mag_filter,
+        min_filter,
+        mipmap_filter,
+        lod_max_clamp,
+        anisotropy_clamp,
+        ..Default::default()
+    })
+}
+
 fn load_material_settings(
     material: &goth_gltf::Material<Extensions>,
 ) -> shared_structs::MaterialSettings {
@@ -348,26 +648,26 @@ fn load_material_settings(
         .map(|emissive_strength| emissive_strength.emissive_strength)
         .unwrap_or(1.0);
 
-    let texture_transform = pbr
-        .base_color_texture
-        .as_ref()
-        .map(|info| info.extensions)
-        .or_else(|| {
-            pbr.metallic_roughness_texture
-                .as_ref()
-                .map(|info| info.extensions)
-        })
-        .or_else(|| material.normal_texture.as_ref().map(|info| info.extensions))
-        .or_else(|| {
-            material
-                .emissive_texture
-                .as_ref()
-                .map(|info| info.extensions)
-        })
-        .and_then(|extensions| extensions.khr_texture_transform);
-
     let emissive_factor = Vec3::from(material.emissive_factor) * emissive_strength;
 
+    // Only the factors of these extensions are used, their textures aren't loaded yet. The fragment
+    // shaders sample 13 of the 16 textures that WebGPU guarantees per stage, which leaves room for
+    // some of them but not for the 5 textures of clearcoat and sheen together.
+
+    let specular = material.extensions.khr_materials_specular.as_ref();
+
+    let specular_colour_factor = specular
+        .map(|specular| Vec3::from(specular.specular_color_factor))
+        .unwrap_or(Vec3::ONE);
+
+    let clearcoat = material.extensions.khr_materials_clearcoat.as_ref();
+
+    let sheen = material.extensions.khr_materials_sheen.as_ref();
+
+    let sheen_colour_factor = sheen
+        .map(|sheen| Vec3::from(sheen.sheen_color_factor))
+        .unwrap_or(Vec3::ZERO);
+
     let mut binary_settings = shared_structs::BinaryMaterialSettings::default();
 
     if unlit {
@@ -387,14 +687,87 @@ fn load_material_settings(
             .as_ref()
             .map(|info| info.scale)
             .unwrap_or(1.0),
-        texture_transform_offset: texture_transform
-            .map(|transform| Vec2::from(transform.offset))
-            .unwrap_or(Vec2::ZERO),
-        texture_transform_scale: texture_transform
-            .map(|transform| Vec2::from(transform.scale))
-            .unwrap_or(Vec2::ONE),
-        texture_transform_rotation: texture_transform
-            .map(|transform| transform.rotation)
+        occlusion_strength: material
+            .occlusion_texture
+            .as_ref()
+            .map(|info| info.strength)
+            .unwrap_or(1.0),
+        albedo_transform: pbr
+            .base_color_texture
+            .as_ref()
+            .map(|info| texture_transform(info.tex_coord, info.extensions.khr_texture_transform))
+            .unwrap_or(shared_structs::TextureTransform::IDENTITY),
+        metallic_roughness_transform: pbr
+            .metallic_roughness_texture
+            .as_ref()
+            .map(|info| texture_transform(info.tex_coord, info.extensions.khr_texture_transform))
+            .unwrap_or(shared_structs::TextureTransform::IDENTITY),
+        normal_transform: material
+            .normal_texture
+            .as_ref()
+            .map(|info| texture_transform(info.tex_coord, info.extensions.khr_texture_transform))
+            .unwrap_or(shared_structs::TextureTransform::IDENTITY),
+        emissive_transform: material
+            .emissive_texture
+            .as_ref()
+            .map(|info| texture_transform(info.tex_coord, info.extensions.khr_texture_transform))
+            .unwrap_or(shared_structs::TextureTransform::IDENTITY),
+        occlusion_transform: material
+            .occlusion_texture
+            .as_ref()
+            .map(|info| texture_transform(info.tex_coord, info.extensions.khr_texture_transform))
+            .unwrap_or(shared_structs::TextureTransform::IDENTITY),
+        alpha_cutoff: material.alpha_cutoff.unwrap_or(0.5),
+        index_of_refraction: material
+            .extensions
+            .khr_materials_ior
+            .as_ref()
+            .map(|ior| ior.ior)
+            .unwrap_or(1.5),
+        specular_factor: specular
+            .map(|specular| specular.specular_factor)
+            .unwrap_or(1.0),
+        specular_colour_factor_x: specular_colour_factor.x,
+        specular_colour_factor_y: specular_colour_factor.y,
+        specular_colour_factor_z: specular_colour_factor.z,
+        clearcoat_factor: clearcoat
+            .map(|clearcoat| clearcoat.clearcoat_factor)
             .unwrap_or(0.0),
+        clearcoat_roughness_factor: clearcoat
+            .map(|clearcoat| clearcoat.clearcoat_roughness_factor)
+            .unwrap_or(0.0),
+        sheen_colour_factor_x: sheen_colour_factor.x,
+        sheen_colour_factor_y: sheen_colour_factor.y,
+        sheen_colour_factor_z: sheen_colour_factor.z,
+        sheen_roughness_factor: sheen
+            .map(|sheen| sheen.sheen_roughness_factor)
+            .unwrap_or(0.0),
+        transmission_factor: material
+            .extensions
+            .khr_materials_transmission
+            .as_ref()
+            .map(|transmission| transmission.transmission_factor)
+            .unwrap_or(0.0),
+        _padding: Default::default(),
+    }
+}
+
+// `KHR_texture_transform` can override the texCoord set of the texture.
+fn texture_transform(
+    tex_coord: usize,
+    khr_texture_transform: Option<goth_gltf::extensions::KhrTextureTransform>,
+) -> shared_structs::TextureTransform {
+    match khr_texture_transform {
+        Some(transform) => shared_structs::TextureTransform {
+            offset: Vec2::from(transform.offset),
+            scale: Vec2::from(transform.scale),
+            rotation: transform.rotation,
+            tex_coord: transform.tex_coord.unwrap_or(tex_coord) as u32,
+            _padding: Default::default(),
+        },
+        None => shared_structs::TextureTransform {
+            tex_coord: tex_coord as u32,
+            ..shared_structs::TextureTransform::IDENTITY
+        },
     }
 }
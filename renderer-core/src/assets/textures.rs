@@ -10,6 +10,15 @@ pub struct Settings {
     pub anisotropy_clamp: u16,
 }
 
+// Passed to the callbacks of KTX2 loads.
+#[derive(Clone, Copy, Debug)]
+pub enum MipLevelProgress {
+    // The number of mip levels that will be uploaded, reported before any of them are.
+    Expected(u32),
+    // A mip level has been uploaded.
+    Uploaded(u32),
+}
+
 #[derive(Clone)]
 pub struct Context<T> {
     pub pipelines: Arc<crate::Pipelines>,
@@ -330,16 +339,20 @@ impl<'a> ImageSource<'a> {
     }
 }
 
-pub(super) async fn load_image_with_mime_type<T: HttpClient>(
+pub(super) async fn load_image_with_mime_type<
+    F: Fn(MipLevelProgress) + Send + 'static,
+    T: HttpClient,
+>(
     source: ImageSource<'_>,
     srgb: bool,
     mime_type: Option<&str>,
     context: &Context<T>,
+    on_level_load: F,
 ) -> anyhow::Result<Arc<Texture>> {
     match (mime_type, source.extension()) {
         (Some("image/ktx2"), _) | (_, Some("ktx2")) => match source {
-            ImageSource::Url(url) => load_ktx2_async(context, &url, srgb, |_| {}).await,
-            ImageSource::Bytes(bytes) => load_ktx2_from_bytes(context, bytes, srgb, |_| {}),
+            ImageSource::Url(url) => load_ktx2_async(context, &url, srgb, on_level_load).await,
+            ImageSource::Bytes(bytes) => load_ktx2_from_bytes(context, bytes, srgb, on_level_load),
         },
         _ => {
             let (image, _size) = load_image_crate_image(
@@ -613,7 +626,7 @@ fn downscaling_for_max_size(texture_size: u32, max_size: u32) -> u32 {
     (texture_size_log as u32).saturating_sub(max_size_log as u32)
 }
 
-pub async fn load_ktx2_async<F: Fn(u32) + Send + 'static, T: HttpClient>(
+pub async fn load_ktx2_async<F: Fn(MipLevelProgress) + Send + 'static, T: HttpClient>(
     context: &Context<T>,
     url: &url::Url,
     srgb: bool,
@@ -771,6 +784,10 @@ pub async fn load_ktx2_async<F: Fn(u32) + Send + 'static, T: HttpClient>(
         view_formats: &[],
     };
 
+    on_level_load(MipLevelProgress::Expected(
+        texture_descriptor.mip_level_count,
+    ));
+
     let texture = Arc::new(Texture::new_with_view_dimension(
         context.device.create_texture(&texture_descriptor),
         if header.pixel_depth > 1 {
@@ -844,20 +861,20 @@ pub async fn load_ktx2_async<F: Fn(u32) + Send + 'static, T: HttpClient>(
             &texture_descriptor,
         );
 
-        on_level_load(i as u32 - down_scaling_level)
+        on_level_load(MipLevelProgress::Uploaded(i as u32 - down_scaling_level))
     }
 
-    // Load all other mips in the background.
+    // Load all other mips in the background, stopping if the texture gets dropped.
     spawn({
         //let url = Rc::clone(url);
-        let texture = Arc::clone(&texture);
+        let texture = Arc::downgrade(&texture);
         let url = url.clone();
         let http_client = context.http_client.clone();
         let queue = context.queue.clone();
 
         async move {
             for (i, level_index) in levels {
-                if i < down_scaling_level as usize {
+                if i < down_scaling_level as usize || texture.strong_count() == 0 {
                     return Ok(());
                 }
 
@@ -907,6 +924,11 @@ pub async fn load_ktx2_async<F: Fn(u32) + Send + 'static, T: HttpClient>(
                     format_bytes
                 };
 
+                let texture = match texture.upgrade() {
+                    Some(texture) => texture,
+                    None => return Ok(()),
+                };
+
                 write_bytes_to_texture(
                     &queue,
                     &texture.texture,
@@ -915,7 +937,7 @@ pub async fn load_ktx2_async<F: Fn(u32) + Send + 'static, T: HttpClient>(
                     &texture_descriptor,
                 );
 
-                on_level_load(i as u32 - down_scaling_level)
+                on_level_load(MipLevelProgress::Uploaded(i as u32 - down_scaling_level))
             }
 
             Ok(())
@@ -926,7 +948,7 @@ pub async fn load_ktx2_async<F: Fn(u32) + Send + 'static, T: HttpClient>(
 }
 
 // Todo: deduplicate code between this and the async function.
-pub(crate) fn load_ktx2_from_bytes<F: Fn(u32) + Send + 'static, T: HttpClient>(
+pub(crate) fn load_ktx2_from_bytes<F: Fn(MipLevelProgress) + Send + 'static, T: HttpClient>(
     context: &Context<T>,
     bytes: &[u8],
     srgb: bool,
@@ -1031,6 +1053,10 @@ pub(crate) fn load_ktx2_from_bytes<F: Fn(u32) + Send + 'static, T: HttpClient>(
         view_formats: &[],
     };
 
+    on_level_load(MipLevelProgress::Expected(
+        texture_descriptor.mip_level_count,
+    ));
+
     let mut texture_bytes = Vec::new();
 
     for (i, level_index) in level_indices.into_iter().enumerate() {
@@ -1084,7 +1110,7 @@ pub(crate) fn load_ktx2_from_bytes<F: Fn(u32) + Send + 'static, T: HttpClient>(
 
         texture_bytes.extend_from_slice(&bytes_to_upload);
 
-        on_level_load(i as u32 - down_scaling_level)
+        on_level_load(MipLevelProgress::Uploaded(i as u32 - down_scaling_level))
     }
 
     Ok(Arc::new(Texture::new(
@@ -110,7 +110,14 @@ impl BindGroupLayouts {
                     texture_entry(2),
                     texture_entry(3),
                     uniform_entry(4, wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT),
+                    // The albedo sampler.
                     sampler_entry(5),
+                    texture_entry(6),
+                    // The normal, metallic roughness, emissive and occlusion samplers.
+                    sampler_entry(7),
+                    sampler_entry(8),
+                    sampler_entry(9),
+                    sampler_entry(10),
                 ],
             }),
             tonemap: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -134,7 +141,22 @@ impl BindGroupLayouts {
             }),
             joints: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("joints bind group layout"),
-                entries: &[uniform_entry(0, wgpu::ShaderStages::VERTEX)],
+                entries: &[
+                    uniform_entry(0, wgpu::ShaderStages::VERTEX),
+                    // morph weights
+                    uniform_entry(1, wgpu::ShaderStages::VERTEX),
+                    // morph targets
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        count: None,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                    },
+                ],
             }),
         }
     }
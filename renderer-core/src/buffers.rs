@@ -182,6 +182,49 @@ impl<T: bytemuck::Pod> AllocatedBuffer<T> {
         (range, if resized { Some(buffer) } else { None })
     }
 
+    // Return a range to the allocator so that it can be reused by later inserts.
+    pub fn free(&self, range: Range<u32>) {
+        free_range(&self.allocator, range);
+    }
+
+    // Move the `movable` ranges to the front of a fresh buffer, shrinking it if less than
+    // `shrink_threshold` of the capacity is in use. Allocated ranges that aren't in `movable`
+    // stay where they are. The returned relocations need to be applied to anything that refers
+    // to the moved ranges.
+    pub fn compact(
+        &self,
+        movable: &[Range<u32>],
+        shrink_threshold: f32,
+        device: &wgpu::Device,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) -> Relocations {
+        let mut allocator = self.allocator.lock();
+
+        let plan = match plan_compaction(&mut allocator, movable, shrink_threshold) {
+            Some(plan) => plan,
+            None => return Relocations::default(),
+        };
+
+        log::info!(
+            "Compacting {} to a capacity of {} ({} ranges moved)",
+            self.label,
+            plan.new_capacity,
+            plan.relocations.len()
+        );
+
+        replace_with_compacted_buffer(
+            &self.buffer,
+            self.label,
+            self.usage,
+            &plan,
+            size_of::<T>(),
+            device,
+            command_encoder,
+        );
+
+        plan.relocations
+    }
+
     fn resize(
         allocator: &mut range_alloc::RangeAllocator<u32>,
         buffer: &ArcSwap<wgpu::Buffer>,
@@ -208,7 +251,7 @@ impl<T: bytemuck::Pod> AllocatedBuffer<T> {
             new_capacity
         );
 
-        allocator.grow_to(new_capacity);
+        grow_allocator(allocator, new_capacity);
 
         let new_buffer = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
             label: Some(label),
@@ -258,6 +301,21 @@ impl IndexBuffer {
         range
     }
 
+    pub fn free(&self, range: Range<u32>) {
+        self.inner.free(range);
+    }
+
+    pub fn compact(
+        &self,
+        movable: &[Range<u32>],
+        shrink_threshold: f32,
+        device: &wgpu::Device,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) -> Relocations {
+        self.inner
+            .compact(movable, shrink_threshold, device, command_encoder)
+    }
+
     pub fn buffer(&self) -> arc_swap::Guard<Arc<wgpu::Buffer>> {
         self.inner.buffer.load()
     }
@@ -268,6 +326,7 @@ pub struct RawVertexBuffers<T> {
     pub normal: T,
     pub uv: T,
     pub lightmap_uv: T,
+    pub colour: T,
 }
 
 impl RawVertexBuffers<ArcSwap<wgpu::Buffer>> {
@@ -277,6 +336,7 @@ impl RawVertexBuffers<ArcSwap<wgpu::Buffer>> {
             normal: self.normal.load(),
             uv: self.uv.load(),
             lightmap_uv: self.lightmap_uv.load(),
+            colour: self.colour.load(),
         }
     }
 }
@@ -315,6 +375,12 @@ impl VertexBuffers {
                     capacity,
                     size_of::<Vec2>(),
                 )),
+                colour: ArcSwap::from(create_buffer(
+                    device,
+                    "colour buffer",
+                    capacity,
+                    size_of::<[u8; 4]>(),
+                )),
             },
         }
     }
@@ -326,6 +392,7 @@ impl VertexBuffers {
         normals: &[Vec3],
         uvs: &[Vec2],
         lightmap_uvs: &[Vec2],
+        colours: &[[u8; 4]],
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         command_encoder: &mut wgpu::CommandEncoder,
@@ -334,6 +401,7 @@ impl VertexBuffers {
 
         debug_assert_eq!(positions.len(), normals.len());
         debug_assert_eq!(positions.len(), uvs.len());
+        debug_assert_eq!(positions.len(), colours.len());
 
         let (buffers, range) = {
             let mut allocator = self.allocator.lock();
@@ -345,6 +413,7 @@ impl VertexBuffers {
                         normal: self.buffers.normal.load_full(),
                         uv: self.buffers.uv.load_full(),
                         lightmap_uv: self.buffers.lightmap_uv.load_full(),
+                        colour: self.buffers.colour.load_full(),
                     };
 
                     (buffers, range)
@@ -387,9 +456,67 @@ impl VertexBuffers {
             bytemuck::cast_slice(lightmap_uvs),
         );
 
+        queue.write_buffer(
+            &buffers.colour,
+            size_in_bytes(range.start, size_of::<[u8; 4]>()),
+            bytemuck::cast_slice(colours),
+        );
+
         range
     }
 
+    pub fn free(&self, range: Range<u32>) {
+        free_range(&self.allocator, range);
+    }
+
+    // See `AllocatedBuffer::compact`.
+    pub fn compact(
+        &self,
+        movable: &[Range<u32>],
+        shrink_threshold: f32,
+        device: &wgpu::Device,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) -> Relocations {
+        let mut allocator = self.allocator.lock();
+
+        let plan = match plan_compaction(&mut allocator, movable, shrink_threshold) {
+            Some(plan) => plan,
+            None => return Relocations::default(),
+        };
+
+        log::info!(
+            "Compacting vertex buffers to a capacity of {} ({} ranges moved)",
+            plan.new_capacity,
+            plan.relocations.len()
+        );
+
+        let buffers = &self.buffers;
+
+        for (buffer, label, size_of_field) in [
+            (&buffers.position, "position buffer", size_of::<Vec3>()),
+            (&buffers.normal, "normal buffer", size_of::<Vec3>()),
+            (&buffers.uv, "uv buffer", size_of::<Vec2>()),
+            (
+                &buffers.lightmap_uv,
+                "lightmap uv buffer",
+                size_of::<Vec2>(),
+            ),
+            (&buffers.colour, "colour buffer", size_of::<[u8; 4]>()),
+        ] {
+            replace_with_compacted_buffer(
+                buffer,
+                label,
+                wgpu::BufferUsages::VERTEX,
+                &plan,
+                size_of_field,
+                device,
+                command_encoder,
+            );
+        }
+
+        plan.relocations
+    }
+
     fn resize(
         allocator: &mut range_alloc::RangeAllocator<u32>,
         buffers: &RawVertexBuffers<ArcSwap<wgpu::Buffer>>,
@@ -413,7 +540,7 @@ impl VertexBuffers {
             new_capacity
         );
 
-        allocator.grow_to(new_capacity);
+        grow_allocator(allocator, new_capacity);
 
         let new_buffers = RawVertexBuffers {
             position: create_buffer(device, "position buffer", new_capacity, size_of::<Vec3>()),
@@ -425,6 +552,7 @@ impl VertexBuffers {
                 new_capacity,
                 size_of::<Vec2>(),
             ),
+            colour: create_buffer(device, "colour buffer", new_capacity, size_of::<[u8; 4]>()),
         };
 
         let current_buffers = buffers.load();
@@ -457,11 +585,19 @@ impl VertexBuffers {
             0,
             size_in_bytes(copy_range, size_of::<Vec2>()),
         );
+        command_encoder.copy_buffer_to_buffer(
+            &current_buffers.colour,
+            0,
+            &new_buffers.colour,
+            0,
+            size_in_bytes(copy_range, size_of::<[u8; 4]>()),
+        );
 
         buffers.position.store(new_buffers.position.clone());
         buffers.normal.store(new_buffers.normal.clone());
         buffers.uv.store(new_buffers.uv.clone());
         buffers.lightmap_uv.store(new_buffers.lightmap_uv.clone());
+        buffers.colour.store(new_buffers.colour.clone());
 
         new_buffers
     }
@@ -473,6 +609,8 @@ pub struct RawAnimatedVertexBuffers<T> {
     pub uv: T,
     pub joint_indices: T,
     pub joint_weights: T,
+    pub colour: T,
+    pub morph_index: T,
 }
 
 impl RawAnimatedVertexBuffers<ArcSwap<wgpu::Buffer>> {
@@ -483,6 +621,8 @@ impl RawAnimatedVertexBuffers<ArcSwap<wgpu::Buffer>> {
             uv: self.uv.load(),
             joint_indices: self.joint_indices.load(),
             joint_weights: self.joint_weights.load(),
+            colour: self.colour.load(),
+            morph_index: self.morph_index.load(),
         }
     }
 }
@@ -491,6 +631,228 @@ fn size_in_bytes(size: u32, size_of_field: usize) -> u64 {
     size as u64 * size_of_field as u64
 }
 
+// Where the ranges of a buffer were moved to during compaction.
+#[derive(Default, Debug)]
+pub struct Relocations {
+    // (old range, new start) pairs, sorted by the old range start.
+    moves: Vec<(Range<u32>, u32)>,
+}
+
+impl Relocations {
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    // Get the new location of an offset. Offsets that weren't in a moved range are unchanged.
+    pub fn relocate(&self, offset: u32) -> u32 {
+        let index = self
+            .moves
+            .partition_point(|(old_range, _)| old_range.start <= offset);
+
+        match index.checked_sub(1).map(|index| &self.moves[index]) {
+            Some((old_range, new_start)) if old_range.contains(&offset) => {
+                offset - old_range.start + new_start
+            }
+            _ => offset,
+        }
+    }
+
+    pub fn relocate_range(&self, range: Range<u32>) -> Range<u32> {
+        let start = self.relocate(range.start);
+        start..start + (range.end - range.start)
+    }
+}
+
+struct CompactionPlan {
+    relocations: Relocations,
+    // (source range, destination start) pairs for everything that needs copying into the new
+    // buffer, including ranges that stay in place.
+    copies: Vec<(Range<u32>, u32)>,
+    new_capacity: u32,
+}
+
+// Work out where the movable ranges should go, replacing the allocator with one for the new
+// layout. Returns `None` if nothing would move and the capacity would stay the same.
+fn plan_compaction(
+    allocator: &mut range_alloc::RangeAllocator<u32>,
+    movable: &[Range<u32>],
+    shrink_threshold: f32,
+) -> Option<CompactionPlan> {
+    let old_capacity = allocator.initial_range().end;
+
+    let mut movable: Vec<_> = movable
+        .iter()
+        .filter(|range| !range.is_empty())
+        .cloned()
+        .collect();
+    movable.sort_by_key(|range| range.start);
+
+    // Everything that's allocated but not movable has to stay where it is.
+    let mut pinned = Vec::new();
+
+    for allocated_range in allocator.allocated_ranges() {
+        let mut start = allocated_range.start;
+
+        for range in movable
+            .iter()
+            .filter(|range| range.start < allocated_range.end && range.end > allocated_range.start)
+        {
+            if range.start > start {
+                pinned.push(start..range.start);
+            }
+
+            start = start.max(range.end);
+        }
+
+        if start < allocated_range.end {
+            pinned.push(start..allocated_range.end);
+        }
+    }
+
+    // Move each range into the lowest gap that fits it. Because the movable ranges are sorted,
+    // the ranges placed before a range always end before its original start, so a gap is always
+    // found at or before its original position.
+    let mut occupied = pinned.clone();
+    let mut moves = Vec::new();
+    let mut copies: Vec<_> = pinned
+        .iter()
+        .map(|range| (range.clone(), range.start))
+        .collect();
+
+    for range in &movable {
+        let length = range.end - range.start;
+
+        let mut gap_start = 0;
+        let mut insertion_index = occupied.len();
+
+        for (index, occupied_range) in occupied.iter().enumerate() {
+            if occupied_range.start - gap_start >= length {
+                insertion_index = index;
+                break;
+            }
+
+            gap_start = occupied_range.end;
+        }
+
+        occupied.insert(insertion_index, gap_start..gap_start + length);
+
+        copies.push((range.clone(), gap_start));
+
+        if gap_start != range.start {
+            moves.push((range.clone(), gap_start));
+        }
+    }
+
+    let used = occupied.last().map(|range| range.end).unwrap_or(0);
+
+    let new_capacity = if (used as f32) < old_capacity as f32 * shrink_threshold {
+        used.max(1)
+    } else {
+        old_capacity
+    };
+
+    if moves.is_empty() && new_capacity == old_capacity {
+        return None;
+    }
+
+    *allocator = allocator_with_allocated_ranges(new_capacity, &occupied);
+
+    Some(CompactionPlan {
+        relocations: Relocations { moves },
+        copies,
+        new_capacity,
+    })
+}
+
+// Create a buffer with the capacity of a compaction plan, copy the ranges that are kept into it
+// and swap it in.
+fn replace_with_compacted_buffer(
+    buffer: &ArcSwap<wgpu::Buffer>,
+    label: &str,
+    usage: wgpu::BufferUsages,
+    plan: &CompactionPlan,
+    size_of_field: usize,
+    device: &wgpu::Device,
+    command_encoder: &mut wgpu::CommandEncoder,
+) {
+    let new_buffer = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: size_in_bytes(plan.new_capacity, size_of_field),
+        usage: usage | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    }));
+
+    let old_buffer = buffer.load();
+
+    for (range, new_start) in &plan.copies {
+        command_encoder.copy_buffer_to_buffer(
+            &old_buffer,
+            size_in_bytes(range.start, size_of_field),
+            &new_buffer,
+            size_in_bytes(*new_start, size_of_field),
+            size_in_bytes(range.end - range.start, size_of_field),
+        );
+    }
+
+    buffer.store(new_buffer);
+}
+
+// `RangeAllocator::grow_to` extends the last free range to the new end, which is only correct
+// if that range is at the end of the buffer. Once ranges have been freed that isn't guaranteed,
+// so we rebuild the allocator from the allocated ranges instead.
+fn grow_allocator(allocator: &mut range_alloc::RangeAllocator<u32>, new_capacity: u32) {
+    let allocated_ranges: Vec<_> = allocator.allocated_ranges().collect();
+    *allocator = allocator_with_allocated_ranges(new_capacity, &allocated_ranges);
+}
+
+// Create an allocator for `0..capacity` with the given sorted, non-overlapping ranges already
+// allocated.
+fn allocator_with_allocated_ranges(
+    capacity: u32,
+    allocated_ranges: &[Range<u32>],
+) -> range_alloc::RangeAllocator<u32> {
+    let mut allocator = range_alloc::RangeAllocator::new(0..capacity);
+
+    if capacity == 0 {
+        return allocator;
+    }
+
+    // Allocate everything and then free the gaps between the allocated ranges.
+    allocator
+        .allocate_range(capacity)
+        .expect("allocator is empty");
+
+    let mut gap_start = 0;
+
+    for range in allocated_ranges {
+        if range.start > gap_start {
+            allocator.free_range(gap_start..range.start);
+        }
+
+        gap_start = gap_start.max(range.end);
+    }
+
+    if gap_start < capacity {
+        allocator.free_range(gap_start..capacity);
+    }
+
+    allocator
+}
+
+// `RangeAllocator::free_range` panics on empty ranges, which we get for models without any
+// primitives.
+fn free_range(allocator: &parking_lot::Mutex<range_alloc::RangeAllocator<u32>>, range: Range<u32>) {
+    if range.is_empty() {
+        return;
+    }
+
+    allocator.lock().free_range(range);
+}
+
 fn create_buffer(
     device: &wgpu::Device,
     label: &str,
@@ -547,6 +909,18 @@ impl AnimatedVertexBuffers {
                     capacity,
                     size_of::<Vec4>(),
                 )),
+                colour: ArcSwap::from(create_buffer(
+                    device,
+                    "colour buffer",
+                    capacity,
+                    size_of::<[u8; 4]>(),
+                )),
+                morph_index: ArcSwap::from(create_buffer(
+                    device,
+                    "morph index buffer",
+                    capacity,
+                    size_of::<u32>(),
+                )),
             },
         }
     }
@@ -559,6 +933,8 @@ impl AnimatedVertexBuffers {
         uvs: &[Vec2],
         joint_indices: &[UVec4],
         joint_weights: &[Vec4],
+        colours: &[[u8; 4]],
+        morph_indices: &[u32],
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         command_encoder: &mut wgpu::CommandEncoder,
@@ -569,6 +945,8 @@ impl AnimatedVertexBuffers {
         debug_assert_eq!(positions.len(), uvs.len());
         debug_assert_eq!(positions.len(), joint_indices.len());
         debug_assert_eq!(positions.len(), joint_weights.len());
+        debug_assert_eq!(positions.len(), colours.len());
+        debug_assert_eq!(positions.len(), morph_indices.len());
 
         let (buffers, range) = {
             let mut allocator = self.allocator.lock();
@@ -581,6 +959,8 @@ impl AnimatedVertexBuffers {
                         uv: self.buffers.uv.load_full(),
                         joint_indices: self.buffers.joint_indices.load_full(),
                         joint_weights: self.buffers.joint_weights.load_full(),
+                        colour: self.buffers.colour.load_full(),
+                        morph_index: self.buffers.morph_index.load_full(),
                     };
 
                     (buffers, range)
@@ -629,9 +1009,79 @@ impl AnimatedVertexBuffers {
             bytemuck::cast_slice(joint_weights),
         );
 
+        queue.write_buffer(
+            &buffers.colour,
+            size_in_bytes(range.start, size_of::<[u8; 4]>()),
+            bytemuck::cast_slice(colours),
+        );
+
+        queue.write_buffer(
+            &buffers.morph_index,
+            size_in_bytes(range.start, size_of::<u32>()),
+            bytemuck::cast_slice(morph_indices),
+        );
+
         range
     }
 
+    pub fn free(&self, range: Range<u32>) {
+        free_range(&self.allocator, range);
+    }
+
+    // See `AllocatedBuffer::compact`.
+    pub fn compact(
+        &self,
+        movable: &[Range<u32>],
+        shrink_threshold: f32,
+        device: &wgpu::Device,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) -> Relocations {
+        let mut allocator = self.allocator.lock();
+
+        let plan = match plan_compaction(&mut allocator, movable, shrink_threshold) {
+            Some(plan) => plan,
+            None => return Relocations::default(),
+        };
+
+        log::info!(
+            "Compacting animated vertex buffers to a capacity of {} ({} ranges moved)",
+            plan.new_capacity,
+            plan.relocations.len()
+        );
+
+        let buffers = &self.buffers;
+
+        for (buffer, label, size_of_field) in [
+            (&buffers.position, "position buffer", size_of::<Vec3>()),
+            (&buffers.normal, "normal buffer", size_of::<Vec3>()),
+            (&buffers.uv, "uv buffer", size_of::<Vec2>()),
+            (
+                &buffers.joint_indices,
+                "joint indices buffer",
+                size_of::<UVec4>(),
+            ),
+            (
+                &buffers.joint_weights,
+                "joint weights buffer",
+                size_of::<Vec4>(),
+            ),
+            (&buffers.colour, "colour buffer", size_of::<[u8; 4]>()),
+            (&buffers.morph_index, "morph index buffer", size_of::<u32>()),
+        ] {
+            replace_with_compacted_buffer(
+                buffer,
+                label,
+                wgpu::BufferUsages::VERTEX,
+                &plan,
+                size_of_field,
+                device,
+                command_encoder,
+            );
+        }
+
+        plan.relocations
+    }
+
     fn resize(
         allocator: &mut range_alloc::RangeAllocator<u32>,
         buffers: &RawAnimatedVertexBuffers<ArcSwap<wgpu::Buffer>>,
@@ -655,7 +1105,7 @@ impl AnimatedVertexBuffers {
             new_capacity
         );
 
-        allocator.grow_to(new_capacity);
+        grow_allocator(allocator, new_capacity);
 
         let new_buffers = RawAnimatedVertexBuffers {
             position: create_buffer(device, "position buffer", new_capacity, size_of::<Vec3>()),
@@ -673,6 +1123,13 @@ impl AnimatedVertexBuffers {
                 new_capacity,
                 size_of::<Vec4>(),
             ),
+            colour: create_buffer(device, "colour buffer", new_capacity, size_of::<[u8; 4]>()),
+            morph_index: create_buffer(
+                device,
+                "morph index buffer",
+                new_capacity,
+                size_of::<u32>(),
+            ),
         };
 
         let current_buffers = buffers.load();
@@ -712,6 +1169,20 @@ impl AnimatedVertexBuffers {
             0,
             size_in_bytes(copy_range, size_of::<Vec4>()),
         );
+        command_encoder.copy_buffer_to_buffer(
+            &current_buffers.colour,
+            0,
+            &new_buffers.colour,
+            0,
+            size_in_bytes(copy_range, size_of::<[u8; 4]>()),
+        );
+        command_encoder.copy_buffer_to_buffer(
+            &current_buffers.morph_index,
+            0,
+            &new_buffers.morph_index,
+            0,
+            size_in_bytes(copy_range, size_of::<u32>()),
+        );
 
         buffers.position.store(new_buffers.position.clone());
         buffers.normal.store(new_buffers.normal.clone());
@@ -722,7 +1193,100 @@ impl AnimatedVertexBuffers {
         buffers
             .joint_weights
             .store(new_buffers.joint_weights.clone());
+        buffers.colour.store(new_buffers.colour.clone());
+        buffers.morph_index.store(new_buffers.morph_index.clone());
 
         new_buffers
     }
 }
+
+#[cfg(test)]
+// Ranges of geometry are passed around as slices.
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relocations_move_offsets_inside_moved_ranges() {
+        let relocations = Relocations {
+            moves: vec![(10..20, 0), (30..35, 10)],
+        };
+
+        assert_eq!(relocations.relocate(10), 0);
+        assert_eq!(relocations.relocate(19), 9);
+        assert_eq!(relocations.relocate(32), 12);
+        assert_eq!(relocations.relocate_range(30..35), 10..15);
+    }
+
+    #[test]
+    fn relocations_leave_other_offsets_alone() {
+        let relocations = Relocations {
+            moves: vec![(10..20, 0)],
+        };
+
+        assert_eq!(relocations.relocate(5), 5);
+        assert_eq!(relocations.relocate(20), 20);
+        assert_eq!(Relocations::default().relocate(7), 7);
+    }
+
+    #[test]
+    fn compaction_moves_ranges_to_the_front() {
+        let mut allocator = allocator_with_allocated_ranges(100, &[10..20, 40..50]);
+
+        let plan = plan_compaction(&mut allocator, &[10..20, 40..50], 0.0).unwrap();
+
+        assert_eq!(plan.new_capacity, 100);
+        assert_eq!(plan.relocations.moves, vec![(10..20, 0), (40..50, 10)]);
+        assert_eq!(plan.copies, vec![(10..20, 0), (40..50, 10)]);
+        assert_eq!(
+            allocator.allocated_ranges().collect::<Vec<_>>(),
+            vec![0..20]
+        );
+    }
+
+    #[test]
+    fn compaction_keeps_pinned_ranges_in_place() {
+        let mut allocator = allocator_with_allocated_ranges(100, &[10..20, 30..40, 60..65]);
+
+        let plan = plan_compaction(&mut allocator, &[10..20, 60..65], 0.0).unwrap();
+
+        // The pinned range is copied to where it already was.
+        assert!(plan.copies.contains(&(30..40, 30)));
+        assert_eq!(plan.relocations.moves, vec![(10..20, 0), (60..65, 10)]);
+        assert_eq!(
+            allocator.allocated_ranges().collect::<Vec<_>>(),
+            vec![0..15, 30..40]
+        );
+    }
+
+    #[test]
+    fn compaction_pins_the_unmovable_parts_of_merged_ranges() {
+        // Adjacent allocations are merged by the allocator, so a movable range can be part of a
+        // larger allocated range.
+        let mut allocator = allocator_with_allocated_ranges(100, &[20..50]);
+
+        let plan = plan_compaction(&mut allocator, &[30..40], 0.0).unwrap();
+
+        assert!(plan.copies.contains(&(20..30, 20)));
+        assert!(plan.copies.contains(&(40..50, 40)));
+        assert_eq!(plan.relocations.moves, vec![(30..40, 0)]);
+    }
+
+    #[test]
+    fn compaction_shrinks_below_the_threshold() {
+        let mut allocator = allocator_with_allocated_ranges(100, &[50..60]);
+
+        let plan = plan_compaction(&mut allocator, &[50..60], 0.5).unwrap();
+
+        assert_eq!(plan.new_capacity, 10);
+        assert_eq!(allocator.initial_range(), &(0..10));
+    }
+
+    #[test]
+    fn compaction_does_nothing_if_already_compact() {
+        let mut allocator = allocator_with_allocated_ranges(100, &[0..60]);
+
+        assert!(plan_compaction(&mut allocator, &[0..60], 0.5).is_none());
+        assert!(plan_compaction(&mut allocator, &[], 0.5).is_none());
+    }
+}
@@ -75,7 +75,7 @@ impl CullingFrustum {
 pub fn test_using_separating_axis_theorem(
     frustum: CullingFrustum,
     view: Mat4,
-    similarity: gltf_helpers::Similarity,
+    transform: gltf_helpers::Transform,
     aabb: &BoundingBox,
 ) -> bool {
     // Near, far
@@ -95,7 +95,7 @@ pub fn test_using_separating_axis_theorem(
 
     // Transform corners
     // This only translates to our OBB if our transform is affine
-    let corners = corners.map(|corner| (view * (similarity * corner).extend(1.0)).truncate());
+    let corners = corners.map(|corner| (view * (transform * corner).extend(1.0)).truncate());
 
     struct OrientatedBoundingBox {
         center: Vec3,
@@ -380,15 +380,15 @@ impl BoundingSphere {
 
 pub fn test_bounding_sphere(
     bounding_sphere: BoundingSphere,
-    transform: gltf_helpers::Similarity,
+    transform: gltf_helpers::Transform,
     params: BoundingSphereCullingParams,
 ) -> bool {
-    let mut center = transform.translation;
+    let mut center = transform.translation();
     center = (params.view * center.extend(1.0)).truncate();
     // in the view, +z = back so we flip it.
     center.z = -center.z;
 
-    let radius = bounding_sphere.radius * transform.scale;
+    let radius = bounding_sphere.radius * transform.max_scale();
 
     let mut visible = center.z + radius > params.z_near;
 
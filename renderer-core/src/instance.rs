@@ -10,11 +10,17 @@ pub struct LineVertex {
 #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 #[repr(C)]
 pub struct GpuInstance {
-    pub similarity: gltf_helpers::Similarity,
-    pub joints_offset: u32,
+    pub transform: shared_structs::AffineTransform,
     pub material_index: u32,
-    pub is_lightmapped: u32,
-    pub _padding: u32,
+    // `shared_structs::INSTANCE_*` bits.
+    pub flags: u16,
+    // Only used by animated models. These are packed into 16 bits where possible so that the
+    // instance stays at 64 bytes with the larger affine transform.
+    pub num_morph_targets: u16,
+    // In vec4s from the start of the joint buffer.
+    pub joints_offset: u16,
+    pub morph_weights_offset: u16,
+    pub morph_target_stride: u32,
 }
 
 #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -30,14 +36,17 @@ pub struct ParticleInstance {
     pub lut_y_index: f32,
 }
 
-pub type Instance = gltf_helpers::Similarity;
+pub type Instance = gltf_helpers::Transform;
 
 #[cfg(feature = "wasm")]
-pub fn instance_from_transform(transform: web_sys::XrRigidTransform, scale: f32) -> Instance {
+pub fn instance_from_transform(
+    transform: web_sys::XrRigidTransform,
+    scale: f32,
+) -> gltf_helpers::Similarity {
     let rotation = transform.orientation();
     let position = transform.position();
 
-    Instance {
+    gltf_helpers::Similarity {
         translation: glam::DVec3::new(position.x(), position.y(), position.z()).as_vec3(),
         rotation: glam::DQuat::from_xyzw(rotation.x(), rotation.y(), rotation.z(), rotation.w())
             .as_f32(),
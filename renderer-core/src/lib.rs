@@ -19,7 +19,7 @@ pub use shared_structs;
 pub use bind_group_layouts::BindGroupLayouts;
 pub use buffers::{
     AllocatedBuffer, AnimatedVertexBuffers, IndexBuffer, RawAnimatedVertexBuffers,
-    RawVertexBuffers, VecGpuBuffer, VertexBuffers,
+    RawVertexBuffers, Relocations, VecGpuBuffer, VertexBuffers,
 };
 pub use instance::{GpuInstance, Instance, LineVertex};
 pub use pipelines::{PipelineOptions, Pipelines};
@@ -249,7 +249,7 @@ impl Texture {
 pub fn spawn<F: std::future::Future<Output = anyhow::Result<()>> + 'static>(future: F) {
     wasm_bindgen_futures::spawn_local(async move {
         if let Err(error) = future.await {
-            log::error!("{}", error);
+            log::error!("{:#}", error);
         }
     });
 }
@@ -261,7 +261,33 @@ where
 {
     async_std::task::spawn(async move {
         if let Err(error) = future.await {
-            log::error!("{}", error);
+            log::error!("{:#}", error);
         }
     });
 }
+
+// Spawn a future that stops running (dropping any in-progress requests) once the `AbortHandle`
+// paired with the registration is aborted.
+#[cfg(feature = "wasm")]
+pub fn spawn_abortable<F: std::future::Future<Output = anyhow::Result<()>> + 'static>(
+    future: F,
+    abort_registration: futures::future::AbortRegistration,
+) {
+    spawn(async move {
+        futures::future::Abortable::new(future, abort_registration)
+            .await
+            .unwrap_or(Ok(()))
+    });
+}
+
+#[cfg(not(feature = "wasm"))]
+pub fn spawn_abortable<F: std::future::Future<Output = anyhow::Result<()>> + Send + 'static>(
+    future: F,
+    abort_registration: futures::future::AbortRegistration,
+) {
+    spawn(async move {
+        futures::future::Abortable::new(future, abort_registration)
+            .await
+            .unwrap_or(Ok(()))
+    });
+}
@@ -75,28 +75,33 @@ impl Pipelines {
             // instance
             wgpu::VertexBufferLayout {
                 array_stride: std::mem::size_of::<super::GpuInstance>() as u64,
-                attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Uint32, 3 => Uint32, 4 => Uint32],
+                attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4, 3 => Uint32, 4 => Uint16x2, 5 => Uint16x2],
                 step_mode: wgpu::VertexStepMode::Instance,
             },
-            // position, normal, uv, lightmap uv
+            // position, normal, uv, lightmap uv, colour
             wgpu::VertexBufferLayout {
                 array_stride: 3 * 4,
                 step_mode: wgpu::VertexStepMode::Vertex,
-                attributes: &wgpu::vertex_attr_array![5 => Float32x3],
+                attributes: &wgpu::vertex_attr_array![6 => Float32x3],
             },
             wgpu::VertexBufferLayout {
                 array_stride: 3 * 4,
-                attributes: &wgpu::vertex_attr_array![6 => Float32x3],
+                attributes: &wgpu::vertex_attr_array![7 => Float32x3],
                 step_mode: wgpu::VertexStepMode::Vertex,
             },
             wgpu::VertexBufferLayout {
                 array_stride: 2 * 4,
-                attributes: &wgpu::vertex_attr_array![7 => Float32x2],
+                attributes: &wgpu::vertex_attr_array![8 => Float32x2],
                 step_mode: wgpu::VertexStepMode::Vertex,
             },
             wgpu::VertexBufferLayout {
                 array_stride: 2 * 4,
-                attributes: &wgpu::vertex_attr_array![8 => Float32x2],
+                attributes: &wgpu::vertex_attr_array![9 => Float32x2],
+                step_mode: wgpu::VertexStepMode::Vertex,
+            },
+            wgpu::VertexBufferLayout {
+                array_stride: 4,
+                attributes: &wgpu::vertex_attr_array![10 => Unorm8x4],
                 step_mode: wgpu::VertexStepMode::Vertex,
             },
         ];
@@ -109,7 +114,7 @@ impl Pipelines {
             },
             wgpu::VertexBufferLayout {
                 array_stride: std::mem::size_of::<super::GpuInstance>() as u64,
-                attributes: &wgpu::vertex_attr_array![1 => Float32x4, 2 => Float32x4, 3 => Uint32, 4 => Uint32],
+                attributes: &wgpu::vertex_attr_array![1 => Float32x4, 2 => Float32x4, 3 => Float32x4, 4 => Uint32, 5 => Uint16x2, 6 => Uint16x2],
                 step_mode: wgpu::VertexStepMode::Instance,
             },
         ];
@@ -129,35 +134,47 @@ impl Pipelines {
             // instance
             wgpu::VertexBufferLayout {
                 array_stride: std::mem::size_of::<super::GpuInstance>() as u64,
-                attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Uint32, 3 => Uint32, 4 => Uint32],
+                attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4, 2 => Float32x4, 3 => Uint32, 4 => Uint16x2, 5 => Uint16x2, 12 => Uint32],
                 step_mode: wgpu::VertexStepMode::Instance,
             },
             // position, normal, uv, joint indices, joint weights
             wgpu::VertexBufferLayout {
                 array_stride: 3 * 4,
                 step_mode: wgpu::VertexStepMode::Vertex,
-                attributes: &wgpu::vertex_attr_array![5 => Float32x3],
+                attributes: &wgpu::vertex_attr_array![6 => Float32x3],
             },
             wgpu::VertexBufferLayout {
                 array_stride: 3 * 4,
-                attributes: &wgpu::vertex_attr_array![6 => Float32x3],
+                attributes: &wgpu::vertex_attr_array![7 => Float32x3],
                 step_mode: wgpu::VertexStepMode::Vertex,
             },
             wgpu::VertexBufferLayout {
                 array_stride: 2 * 4,
-                attributes: &wgpu::vertex_attr_array![7 => Float32x2],
+                attributes: &wgpu::vertex_attr_array![8 => Float32x2],
                 step_mode: wgpu::VertexStepMode::Vertex,
             },
             // joint indices
             wgpu::VertexBufferLayout {
                 array_stride: 4 * 4,
-                attributes: &wgpu::vertex_attr_array![8 => Uint32x4],
+                attributes: &wgpu::vertex_attr_array![9 => Uint32x4],
                 step_mode: wgpu::VertexStepMode::Vertex,
             },
             // joint weights
             wgpu::VertexBufferLayout {
                 array_stride: 4 * 4,
-                attributes: &wgpu::vertex_attr_array![9 => Float32x4],
+                attributes: &wgpu::vertex_attr_array![10 => Float32x4],
+                step_mode: wgpu::VertexStepMode::Vertex,
+            },
+            // colour
+            wgpu::VertexBufferLayout {
+                array_stride: 4,
+                attributes: &wgpu::vertex_attr_array![11 => Unorm8x4],
+                step_mode: wgpu::VertexStepMode::Vertex,
+            },
+            // morph index
+            wgpu::VertexBufferLayout {
+                array_stride: 4,
+                attributes: &wgpu::vertex_attr_array![13 => Uint32],
                 step_mode: wgpu::VertexStepMode::Vertex,
             },
         ];
@@ -410,6 +427,8 @@ impl Pipelines {
                         }),
                     },
                 },
+                // Nothing is rendered with MSAA, so alpha-to-coverage isn't available and masked
+                // materials are discarded below their alpha cutoff in the fragment shader instead.
                 alpha_clipped: permutations::ModelTypes {
                     stationary: permutations::FaceSides {
                         single: device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -1,12 +1,13 @@
 #![cfg_attr(target_arch = "spirv", no_std)]
 
 use shared_structs::{
-    eval_spherical_harmonics_nonlinear, BinaryMaterialSettings, JointTransform, MaterialSettings,
-    Settings, Uniforms,
+    eval_spherical_harmonics_nonlinear, AffineTransform, BinaryMaterialSettings, MaterialSettings,
+    Settings, Uniforms, INSTANCE_AFFINE_JOINTS, INSTANCE_LIGHTMAPPED, MAX_JOINT_VEC4S,
+    MAX_MORPH_WEIGHTS, MORPH_TARGET_TEXTURE_WIDTH,
 };
 use spirv_std::{
     arch::IndexUnchecked,
-    glam::{self, Mat3, UVec4, Vec2, Vec3, Vec4},
+    glam::{self, IVec2, Mat3, UVec2, UVec4, Vec2, Vec3, Vec4},
     num_traits::Float,
     spirv, Image, Sampler,
 };
@@ -24,37 +25,43 @@ pub use single_view::{
 
 #[spirv(vertex)]
 pub fn vertex(
-    instance_translation_and_scale: Vec4,
-    instance_rotation: glam::Quat,
-    _joints_offset: u32,
+    instance_x_row: Vec4,
+    instance_y_row: Vec4,
+    instance_z_row: Vec4,
     material_index: u32,
-    is_lightmapped: u32,
+    flags_and_num_morph_targets: UVec2,
+    _joints_and_morph_weights_offsets: UVec2,
     position: Vec3,
     normal: Vec3,
     uv: Vec2,
     lightmap_uv: Vec2,
+    colour: Vec4,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
-    #[spirv(descriptor_set = 1, binding = 4, uniform)] material_settings: &MaterialSettings,
     #[spirv(position)] builtin_pos: &mut Vec4,
     #[spirv(view_index)] view_index: i32,
     out_position: &mut Vec3,
     out_normal: &mut Vec3,
     out_uv: &mut Vec2,
     out_lightmap_uv: &mut Vec2,
+    out_colour: &mut Vec4,
     #[spirv(flat)] out_material_index: &mut u32,
     #[spirv(flat)] out_is_lightmapped: &mut u32,
 ) {
-    let instance_scale = instance_translation_and_scale.w;
-    let instance_translation = instance_translation_and_scale.truncate();
+    let instance_transform = AffineTransform {
+        x_row: instance_x_row,
+        y_row: instance_y_row,
+        z_row: instance_z_row,
+    };
 
-    let position = instance_translation + (instance_rotation * (instance_scale * position));
+    let position = instance_transform * position;
     *builtin_pos = uniforms.projection_view(view_index) * position.extend(1.0);
     *out_position = position;
-    *out_normal = instance_rotation * normal;
-    *out_uv = material_settings.transform_uv(uv);
+    *out_normal = instance_transform.transform_normal(normal).normalize();
+    *out_uv = uv;
     *out_material_index = material_index;
     *out_lightmap_uv = lightmap_uv;
-    *out_is_lightmapped = is_lightmapped;
+    *out_colour = colour;
+    *out_is_lightmapped = flags_and_num_morph_targets.x & INSTANCE_LIGHTMAPPED;
 
     if uniforms.settings.contains(Settings::FLIP_VIEWPORT) {
         builtin_pos.y = -builtin_pos.y;
@@ -63,69 +70,142 @@ pub fn vertex(
 
 #[spirv(vertex)]
 pub fn animated_vertex(
-    instance_translation_and_scale: Vec4,
-    instance_rotation: glam::Quat,
-    joints_offset: u32,
+    instance_x_row: Vec4,
+    instance_y_row: Vec4,
+    instance_z_row: Vec4,
     material_index: u32,
-    is_lightmapped: u32,
+    flags_and_num_morph_targets: UVec2,
+    joints_and_morph_weights_offsets: UVec2,
     position: Vec3,
     normal: Vec3,
     uv: Vec2,
     joint_indices: UVec4,
     joint_weights: Vec4,
+    colour: Vec4,
+    morph_target_stride: u32,
+    morph_index: u32,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
-    #[spirv(descriptor_set = 1, binding = 4, uniform)] material_settings: &MaterialSettings,
-    #[spirv(descriptor_set = 2, binding = 0, uniform)]
-    joint_transforms: &[JointTransform; JointTransform::MAX_COUNT],
+    #[spirv(descriptor_set = 2, binding = 0, uniform)] joint_transforms: &[Vec4; MAX_JOINT_VEC4S],
+    #[spirv(descriptor_set = 2, binding = 1, uniform)]
+    morph_weights: &[Vec4; MAX_MORPH_WEIGHTS / 4],
+    #[spirv(descriptor_set = 2, binding = 2)] morph_targets: &Image2D,
     #[spirv(position)] builtin_pos: &mut Vec4,
     #[spirv(view_index)] view_index: i32,
     out_position: &mut Vec3,
     out_normal: &mut Vec3,
     out_uv: &mut Vec2,
     out_lightmap_uv: &mut Vec2,
+    out_colour: &mut Vec4,
     #[spirv(flat)] out_material_index: &mut u32,
     #[spirv(flat)] out_is_lightmapped: &mut u32,
 ) {
-    let instance_scale = instance_translation_and_scale.w;
-    let instance_translation = instance_translation_and_scale.truncate();
+    let instance_transform = AffineTransform {
+        x_row: instance_x_row,
+        y_row: instance_y_row,
+        z_row: instance_z_row,
+    };
+
+    let flags = flags_and_num_morph_targets.x;
+    let num_morph_targets = flags_and_num_morph_targets.y;
+    let joints_offset = joints_and_morph_weights_offsets.x;
+    let morph_weights_offset = joints_and_morph_weights_offsets.y;
+
+    let joint_transform = |joint_index: u32| {
+        read_joint_transform(
+            joint_transforms,
+            joints_offset,
+            joint_index,
+            flags & INSTANCE_AFFINE_JOINTS != 0,
+        )
+    };
 
-    let global_joint_indices = joint_indices + joints_offset;
     let joint_weights =
         joint_weights / (joint_weights.x + joint_weights.y + joint_weights.z + joint_weights.w);
 
-    // Calculate the skinned position and normal by multiplying them by the joint transforms and perfoming a weighted average.
+    // Apply the morph targets before skinning. Each target is stored as `morph_target_stride`
+    // position deltas followed by the same number of normal deltas.
 
-    #[rustfmt::skip]
-    let position = unsafe {
-        (*joint_transforms.index_unchecked(global_joint_indices.x as usize) * position * joint_weights.x)
-            + (*joint_transforms.index_unchecked(global_joint_indices.y as usize) * position * joint_weights.y)
-            + (*joint_transforms.index_unchecked(global_joint_indices.z as usize) * position * joint_weights.z)
-            + (*joint_transforms.index_unchecked(global_joint_indices.w as usize) * position * joint_weights.w)
-    };
+    let mut position = position;
+    let mut normal = normal;
 
-    #[rustfmt::skip]
-    let normal = unsafe {
-        (joint_transforms.index_unchecked(global_joint_indices.x as usize).rotation * normal * joint_weights.x)
-            + (joint_transforms.index_unchecked(global_joint_indices.y as usize).rotation * normal * joint_weights.y)
-            + (joint_transforms.index_unchecked(global_joint_indices.z as usize).rotation * normal * joint_weights.z)
-            + (joint_transforms.index_unchecked(global_joint_indices.w as usize).rotation * normal * joint_weights.w)
-    };
+    let mut target = 0;
 
-    let position = instance_translation + (instance_rotation * (instance_scale * position));
+    while target < num_morph_targets {
+        let weight_index = (morph_weights_offset + target) as usize;
+        let weight = unsafe { morph_weights.index_unchecked(weight_index / 4)[weight_index % 4] };
+
+        if weight != 0.0 {
+            let texel = morph_index + target * 2 * morph_target_stride;
+            let fetch_delta = |texel: u32| -> Vec3 {
+                let coord = IVec2::new(
+                    (texel % MORPH_TARGET_TEXTURE_WIDTH) as i32,
+                    (texel / MORPH_TARGET_TEXTURE_WIDTH) as i32,
+                );
+                let delta: Vec4 = morph_targets.fetch(coord);
+                delta.truncate()
+            };
+
+            position += fetch_delta(texel) * weight;
+            normal += fetch_delta(texel + morph_target_stride) * weight;
+        }
+
+        target += 1;
+    }
+
+    // Calculate the skinned position and normal by performing a weighted average of the joint transforms and multiplying them by it.
+
+    let skin_transform = (joint_transform(joint_indices.x) * joint_weights.x)
+        + (joint_transform(joint_indices.y) * joint_weights.y)
+        + (joint_transform(joint_indices.z) * joint_weights.z)
+        + (joint_transform(joint_indices.w) * joint_weights.w);
+
+    let position = instance_transform * (skin_transform * position);
+    let normal = instance_transform.transform_normal(skin_transform.transform_normal(normal));
 
     *builtin_pos = uniforms.projection_view(view_index) * position.extend(1.0);
     *out_position = position;
-    *out_normal = instance_rotation * normal;
-    *out_uv = material_settings.transform_uv(uv);
+    *out_normal = normal.normalize();
+    *out_uv = uv;
     *out_material_index = material_index;
-    *out_lightmap_uv = Vec2::ZERO;
-    *out_is_lightmapped = is_lightmapped;
+    // Animated vertices don't have a second uv set, so textures that use texCoord 1 (such as
+    // occlusion textures) fall back to the first.
+    *out_lightmap_uv = uv;
+    *out_colour = colour;
+    *out_is_lightmapped = flags & INSTANCE_LIGHTMAPPED;
 
     if uniforms.settings.contains(Settings::FLIP_VIEWPORT) {
         builtin_pos.y = -builtin_pos.y;
     }
 }
 
+// Joints are either stored as the three rows of an affine transform, or packed into a translation
+// and uniform scale followed by a rotation.
+fn read_joint_transform(
+    joint_transforms: &[Vec4; MAX_JOINT_VEC4S],
+    joints_offset: u32,
+    joint_index: u32,
+    affine: bool,
+) -> AffineTransform {
+    unsafe {
+        if affine {
+            let start = (joints_offset + joint_index * 3) as usize;
+
+            AffineTransform {
+                x_row: *joint_transforms.index_unchecked(start),
+                y_row: *joint_transforms.index_unchecked(start + 1),
+                z_row: *joint_transforms.index_unchecked(start + 2),
+            }
+        } else {
+            let start = (joints_offset + joint_index * 2) as usize;
+
+            AffineTransform::from_packed_similarity(
+                *joint_transforms.index_unchecked(start),
+                glam::Quat::from_vec4(*joint_transforms.index_unchecked(start + 1)),
+            )
+        }
+    }
+}
+
 struct TextureSampler<'a> {
     sampler: Sampler,
     texture: &'a Image2D,
@@ -151,6 +231,12 @@ struct ExtendedMaterialParams {
     base: glam_pbr::MaterialParams,
     alpha: f32,
     emissive: Vec3,
+    occlusion: f32,
+    clearcoat: f32,
+    clearcoat_roughness: f32,
+    sheen_colour: Vec3,
+    sheen_roughness: f32,
+    transmission: f32,
 }
 
 impl ExtendedMaterialParams {
@@ -158,26 +244,40 @@ impl ExtendedMaterialParams {
         albedo_texture: TextureSampler,
         metallic_roughness_texture: TextureSampler,
         emissive_texture: TextureSampler,
+        occlusion_texture: TextureSampler,
         material_settings: &MaterialSettings,
+        vertex_colour: Vec4,
     ) -> Self {
-        let albedo = albedo_texture.sample() * material_settings.base_color_factor;
+        let albedo = albedo_texture.sample() * material_settings.base_color_factor * vertex_colour;
         let emissive = emissive_texture.sample().truncate() * material_settings.emissive_factor();
 
         let metallic_roughness = metallic_roughness_texture.sample();
         let metallic = metallic_roughness.z * material_settings.metallic_factor;
         let roughness = metallic_roughness.y * material_settings.roughness_factor;
 
+        // https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html#_material_occlusiontextureinfo_strength
+        let occlusion =
+            1.0 + material_settings.occlusion_strength * (occlusion_texture.sample().x - 1.0);
+
         Self {
             base: glam_pbr::MaterialParams {
                 albedo_colour: albedo.truncate(),
                 metallic,
                 perceptual_roughness: glam_pbr::PerceptualRoughness(roughness),
-                index_of_refraction: glam_pbr::IndexOfRefraction::default(),
-                specular_colour: Vec3::ONE,
-                specular_factor: 1.0,
+                index_of_refraction: glam_pbr::IndexOfRefraction(
+                    material_settings.index_of_refraction,
+                ),
+                specular_colour: material_settings.specular_colour_factor(),
+                specular_factor: material_settings.specular_factor,
             },
             alpha: albedo.w,
             emissive,
+            occlusion,
+            clearcoat: material_settings.clearcoat_factor,
+            clearcoat_roughness: material_settings.clearcoat_roughness_factor,
+            sheen_colour: material_settings.sheen_colour_factor(),
+            sheen_roughness: material_settings.sheen_roughness_factor,
+            transmission: material_settings.transmission_factor,
         }
     }
 }
@@ -255,6 +355,7 @@ pub fn fragment(
     normal: Vec3,
     uv: Vec2,
     lightmap_uv: Vec2,
+    colour: Vec4,
     #[spirv(flat)] _material_index: u32,
     #[spirv(flat)] is_lightmapped: u32,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
@@ -272,7 +373,12 @@ pub fn fragment(
     #[spirv(descriptor_set = 1, binding = 2)] metallic_roughness_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 3)] emissive_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 4, uniform)] material_settings: &MaterialSettings,
-    #[spirv(descriptor_set = 1, binding = 5)] texture_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 5)] albedo_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 6)] occlusion_texture: &Image2D,
+    #[spirv(descriptor_set = 1, binding = 7)] normal_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 8)] metallic_roughness_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 9)] emissive_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 10)] occlusion_sampler: &Sampler,
     #[spirv(view_index, flat)] view_index: i32,
     #[spirv(front_facing)] front_facing: bool,
     output: &mut Vec4,
@@ -299,10 +405,36 @@ pub fn fragment(
     };
 
     let material_params = ExtendedMaterialParams::new(
-        TextureSampler::new(albedo_texture, *texture_sampler, uv),
-        TextureSampler::new(metallic_roughness_texture, *texture_sampler, uv),
-        TextureSampler::new(emissive_texture, *texture_sampler, uv),
+        TextureSampler::new(
+            albedo_texture,
+            *albedo_sampler,
+            material_settings
+                .albedo_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
+        TextureSampler::new(
+            metallic_roughness_texture,
+            *metallic_roughness_sampler,
+            material_settings
+                .metallic_roughness_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
+        TextureSampler::new(
+            emissive_texture,
+            *emissive_sampler,
+            material_settings
+                .emissive_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
+        TextureSampler::new(
+            occlusion_texture,
+            *occlusion_sampler,
+            material_settings
+                .occlusion_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
         &material_settings,
+        colour,
     );
 
     if material_settings
@@ -323,9 +455,14 @@ pub fn fragment(
         spherical_harmonics,
         calculate_normal(
             normal,
-            uv,
             view,
-            TextureSampler::new(normal_texture, *texture_sampler, uv),
+            TextureSampler::new(
+                normal_texture,
+                *normal_sampler,
+                material_settings
+                    .normal_transform
+                    .transform_uv(uv, lightmap_uv),
+            ),
             front_facing,
             material_settings.normal_map_scale,
         ),
@@ -340,6 +477,7 @@ pub fn fragment_alpha_clipped(
     normal: Vec3,
     uv: Vec2,
     lightmap_uv: Vec2,
+    colour: Vec4,
     #[spirv(flat)] _material_index: u32,
     #[spirv(flat)] is_lightmapped: u32,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
@@ -357,7 +495,12 @@ pub fn fragment_alpha_clipped(
     #[spirv(descriptor_set = 1, binding = 2)] metallic_roughness_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 3)] emissive_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 4, uniform)] material_settings: &MaterialSettings,
-    #[spirv(descriptor_set = 1, binding = 5)] texture_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 5)] albedo_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 6)] occlusion_texture: &Image2D,
+    #[spirv(descriptor_set = 1, binding = 7)] normal_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 8)] metallic_roughness_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 9)] emissive_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 10)] occlusion_sampler: &Sampler,
     #[spirv(view_index, flat)] view_index: i32,
     #[spirv(front_facing)] front_facing: bool,
     output: &mut Vec4,
@@ -384,25 +527,56 @@ pub fn fragment_alpha_clipped(
     };
 
     let material_params = ExtendedMaterialParams::new(
-        TextureSampler::new(albedo_texture, *texture_sampler, uv),
-        TextureSampler::new(metallic_roughness_texture, *texture_sampler, uv),
-        TextureSampler::new(emissive_texture, *texture_sampler, uv),
+        TextureSampler::new(
+            albedo_texture,
+            *albedo_sampler,
+            material_settings
+                .albedo_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
+        TextureSampler::new(
+            metallic_roughness_texture,
+            *metallic_roughness_sampler,
+            material_settings
+                .metallic_roughness_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
+        TextureSampler::new(
+            emissive_texture,
+            *emissive_sampler,
+            material_settings
+                .emissive_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
+        TextureSampler::new(
+            occlusion_texture,
+            *occlusion_sampler,
+            material_settings
+                .occlusion_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
         &material_settings,
+        colour,
     );
 
     let view = glam_pbr::View((uniforms.eye_position(view_index) - position).normalize());
 
     let normal = calculate_normal(
         normal,
-        uv,
         view,
-        TextureSampler::new(normal_texture, *texture_sampler, uv),
+        TextureSampler::new(
+            normal_texture,
+            *normal_sampler,
+            material_settings
+                .normal_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
         front_facing,
         material_settings.normal_map_scale,
     );
 
     // We can only do this after we've sampled all textures for naga control flow reasons.
-    if material_params.alpha < 0.5 {
+    if material_params.alpha < material_settings.alpha_cutoff {
         spirv_std::arch::kill();
     }
 
@@ -432,6 +606,7 @@ pub fn fragment_alpha_blended(
     normal: Vec3,
     uv: Vec2,
     lightmap_uv: Vec2,
+    colour: Vec4,
     #[spirv(flat)] _material_index: u32,
     #[spirv(flat)] is_lightmapped: u32,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
@@ -449,7 +624,12 @@ pub fn fragment_alpha_blended(
     #[spirv(descriptor_set = 1, binding = 2)] metallic_roughness_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 3)] emissive_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 4, uniform)] material_settings: &MaterialSettings,
-    #[spirv(descriptor_set = 1, binding = 5)] texture_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 5)] albedo_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 6)] occlusion_texture: &Image2D,
+    #[spirv(descriptor_set = 1, binding = 7)] normal_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 8)] metallic_roughness_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 9)] emissive_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 10)] occlusion_sampler: &Sampler,
     #[spirv(view_index, flat)] view_index: i32,
     #[spirv(front_facing)] front_facing: bool,
     output: &mut Vec4,
@@ -476,10 +656,36 @@ pub fn fragment_alpha_blended(
     };
 
     let material_params = ExtendedMaterialParams::new(
-        TextureSampler::new(albedo_texture, *texture_sampler, uv),
-        TextureSampler::new(metallic_roughness_texture, *texture_sampler, uv),
-        TextureSampler::new(emissive_texture, *texture_sampler, uv),
+        TextureSampler::new(
+            albedo_texture,
+            *albedo_sampler,
+            material_settings
+                .albedo_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
+        TextureSampler::new(
+            metallic_roughness_texture,
+            *metallic_roughness_sampler,
+            material_settings
+                .metallic_roughness_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
+        TextureSampler::new(
+            emissive_texture,
+            *emissive_sampler,
+            material_settings
+                .emissive_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
+        TextureSampler::new(
+            occlusion_texture,
+            *occlusion_sampler,
+            material_settings
+                .occlusion_transform
+                .transform_uv(uv, lightmap_uv),
+        ),
         &material_settings,
+        colour,
     );
 
     if material_settings
@@ -500,9 +706,14 @@ pub fn fragment_alpha_blended(
         spherical_harmonics,
         calculate_normal(
             normal,
-            uv,
             view,
-            TextureSampler::new(normal_texture, *texture_sampler, uv),
+            TextureSampler::new(
+                normal_texture,
+                *normal_sampler,
+                material_settings
+                    .normal_transform
+                    .transform_uv(uv, lightmap_uv),
+            ),
             front_facing,
             material_settings.normal_map_scale,
         ),
@@ -518,8 +729,21 @@ fn calculate_lighting_and_tonemap(
     normal: glam_pbr::Normal,
     view: glam_pbr::View,
 ) -> Vec3 {
+    let irradiance = eval_spherical_harmonics_nonlinear(spherical_harmonics, normal.0);
+
+    // Approximate transmission by letting through the light from behind the surface instead of
+    // reflecting it diffusely. There's no refraction or screen-space sampling of what's behind.
+    let transmitted_irradiance = eval_spherical_harmonics_nonlinear(spherical_harmonics, -view.0);
+
     let diffuse_output = material_params.base.diffuse_colour()
-        * eval_spherical_harmonics_nonlinear(spherical_harmonics, normal.0);
+        * irradiance.lerp(transmitted_irradiance, material_params.transmission);
+
+    let normal_dot_view = glam_pbr::Dot::new(&normal, &view).value.max(0.0);
+
+    // The sheen lobe mostly contributes at grazing angles, and more broadly for rougher sheens.
+    let sheen_output = material_params.sheen_colour
+        * irradiance
+        * (1.0 - normal_dot_view).powf(5.0 - 4.0 * material_params.sheen_roughness);
 
     // Bit of a hack. We reduce the direct lighting by this amount during baking, so we need to scale it back up
     // for better specular. If we just divided the diffuse output by this then the indirect lighting would be lowered as well.
@@ -531,7 +755,31 @@ fn calculate_lighting_and_tonemap(
         view,
         material_params.base,
     );
-    let combined_output = diffuse_output + specular_output + material_params.emissive;
+    // The clearcoat is a dielectric layer on top of the base material. It uses the same normal as
+    // the base layer as clearcoat normal maps are not supported.
+    let clearcoat_output = spherical_harmonics_specular_approximation(
+        spherical_harmonics,
+        normal,
+        view,
+        glam_pbr::MaterialParams {
+            albedo_colour: Vec3::ZERO,
+            metallic: 0.0,
+            perceptual_roughness: glam_pbr::PerceptualRoughness(
+                material_params.clearcoat_roughness,
+            ),
+            index_of_refraction: glam_pbr::IndexOfRefraction::default(),
+            specular_colour: Vec3::ONE,
+            specular_factor: 1.0,
+        },
+    );
+    let clearcoat_fresnel = 0.04 + 0.96 * (1.0 - normal_dot_view).powf(5.0);
+
+    let base_output = diffuse_output + specular_output + sheen_output;
+    let layered_output = base_output * (1.0 - material_params.clearcoat * clearcoat_fresnel)
+        + clearcoat_output * material_params.clearcoat;
+
+    // Occlusion only applies to the indirect lighting from the spherical harmonics.
+    let combined_output = layered_output * material_params.occlusion + material_params.emissive;
 
     potentially_tonemap(combined_output, uniforms)
 }
@@ -543,7 +791,6 @@ fn linear_to_srgb_approx(color_linear: Vec3) -> Vec3 {
 
 fn calculate_normal(
     interpolated_normal: Vec3,
-    uv: Vec2,
     view_vector: glam_pbr::View,
     normal_map: TextureSampler,
     front_facing: bool,
@@ -561,7 +808,8 @@ fn calculate_normal(
     // https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html#_material_normaltextureinfo_scale
     let map_normal = (map_normal * Vec3::new(normal_map_scale, normal_map_scale, 1.0)).normalize();
 
-    let normal = (compute_cotangent_frame(normal, -view_vector.0, uv) * map_normal).normalize();
+    let normal =
+        (compute_cotangent_frame(normal, -view_vector.0, normal_map.uv) * map_normal).normalize();
 
     glam_pbr::Normal(normal)
 }
@@ -757,16 +1005,20 @@ fn debug_colour_for_id(id: u32) -> Vec3 {
 #[spirv(vertex)]
 pub fn depth_prepass_vertex(
     position: Vec3,
-    instance_translation_and_scale: Vec4,
-    instance_rotation: glam::Quat,
+    instance_x_row: Vec4,
+    instance_y_row: Vec4,
+    instance_z_row: Vec4,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
     #[spirv(position)] builtin_pos: &mut Vec4,
     #[spirv(view_index)] view_index: i32,
 ) {
-    let instance_scale = instance_translation_and_scale.w;
-    let instance_translation = instance_translation_and_scale.truncate();
+    let instance_transform = AffineTransform {
+        x_row: instance_x_row,
+        y_row: instance_y_row,
+        z_row: instance_z_row,
+    };
 
-    let position = instance_translation + (instance_rotation * (instance_scale * position));
+    let position = instance_transform * position;
     *builtin_pos = uniforms.projection_view(view_index) * position.extend(1.0);
 
     if uniforms.settings.contains(Settings::FLIP_VIEWPORT) {
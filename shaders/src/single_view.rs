@@ -2,43 +2,47 @@ use super::*;
 
 #[spirv(vertex)]
 pub fn vertex(
-    instance_translation_and_scale: Vec4,
-    instance_rotation: glam::Quat,
-    joints_offset: u32,
+    instance_x_row: Vec4,
+    instance_y_row: Vec4,
+    instance_z_row: Vec4,
     material_index: u32,
-    is_lightmapped: u32,
+    flags_and_num_morph_targets: UVec2,
+    joints_and_morph_weights_offsets: UVec2,
     position: Vec3,
     normal: Vec3,
     uv: Vec2,
     lightmap_uv: Vec2,
+    colour: Vec4,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
-    #[spirv(descriptor_set = 1, binding = 4, uniform)] material_settings: &MaterialSettings,
     #[spirv(position)] builtin_pos: &mut Vec4,
     out_position: &mut Vec3,
     out_normal: &mut Vec3,
     out_uv: &mut Vec2,
     out_lightmap_uv: &mut Vec2,
+    out_colour: &mut Vec4,
     #[spirv(flat)] out_material_index: &mut u32,
     #[spirv(flat)] out_is_lightmapped: &mut u32,
 ) {
     super::vertex(
-        instance_translation_and_scale,
-        instance_rotation,
-        joints_offset,
+        instance_x_row,
+        instance_y_row,
+        instance_z_row,
         material_index,
-        is_lightmapped,
+        flags_and_num_morph_targets,
+        joints_and_morph_weights_offsets,
         position,
         normal,
         uv,
         lightmap_uv,
+        colour,
         uniforms,
-        material_settings,
         builtin_pos,
         0,
         out_position,
         out_normal,
         out_uv,
         out_lightmap_uv,
+        out_colour,
         out_material_index,
         out_is_lightmapped,
     );
@@ -46,48 +50,60 @@ pub fn vertex(
 
 #[spirv(vertex)]
 pub fn animated_vertex(
-    instance_translation_and_scale: Vec4,
-    instance_rotation: glam::Quat,
-    joints_offset: u32,
+    instance_x_row: Vec4,
+    instance_y_row: Vec4,
+    instance_z_row: Vec4,
     material_index: u32,
-    is_lightmapped: u32,
+    flags_and_num_morph_targets: UVec2,
+    joints_and_morph_weights_offsets: UVec2,
     position: Vec3,
     normal: Vec3,
     uv: Vec2,
     joint_indices: UVec4,
     joint_weights: Vec4,
+    colour: Vec4,
+    morph_target_stride: u32,
+    morph_index: u32,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
-    #[spirv(descriptor_set = 1, binding = 4, uniform)] material_settings: &MaterialSettings,
-    #[spirv(descriptor_set = 2, binding = 0, uniform)]
-    joint_transforms: &[JointTransform; JointTransform::MAX_COUNT],
+    #[spirv(descriptor_set = 2, binding = 0, uniform)] joint_transforms: &[Vec4; MAX_JOINT_VEC4S],
+    #[spirv(descriptor_set = 2, binding = 1, uniform)]
+    morph_weights: &[Vec4; MAX_MORPH_WEIGHTS / 4],
+    #[spirv(descriptor_set = 2, binding = 2)] morph_targets: &Image2D,
     #[spirv(position)] builtin_pos: &mut Vec4,
     out_position: &mut Vec3,
     out_normal: &mut Vec3,
     out_uv: &mut Vec2,
     out_lightmap_uv: &mut Vec2,
+    out_colour: &mut Vec4,
     #[spirv(flat)] out_material_index: &mut u32,
     #[spirv(flat)] out_is_lightmapped: &mut u32,
 ) {
     super::animated_vertex(
-        instance_translation_and_scale,
-        instance_rotation,
-        joints_offset,
+        instance_x_row,
+        instance_y_row,
+        instance_z_row,
         material_index,
-        is_lightmapped,
+        flags_and_num_morph_targets,
+        joints_and_morph_weights_offsets,
         position,
         normal,
         uv,
         joint_indices,
         joint_weights,
+        colour,
+        morph_target_stride,
+        morph_index,
         uniforms,
-        material_settings,
         joint_transforms,
+        morph_weights,
+        morph_targets,
         builtin_pos,
         0,
         out_position,
         out_normal,
         out_uv,
         out_lightmap_uv,
+        out_colour,
         out_material_index,
         out_is_lightmapped,
     );
@@ -99,6 +115,7 @@ pub fn fragment(
     normal: Vec3,
     uv: Vec2,
     lightmap_uv: Vec2,
+    colour: Vec4,
     #[spirv(flat)] material_index: u32,
     #[spirv(flat)] is_lightmapped: u32,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
@@ -116,7 +133,12 @@ pub fn fragment(
     #[spirv(descriptor_set = 1, binding = 2)] metallic_roughness_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 3)] emissive_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 4, uniform)] material_settings: &MaterialSettings,
-    #[spirv(descriptor_set = 1, binding = 5)] texture_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 5)] albedo_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 6)] occlusion_texture: &Image2D,
+    #[spirv(descriptor_set = 1, binding = 7)] normal_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 8)] metallic_roughness_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 9)] emissive_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 10)] occlusion_sampler: &Sampler,
     #[spirv(front_facing)] front_facing: bool,
     output: &mut Vec4,
 ) {
@@ -125,6 +147,7 @@ pub fn fragment(
         normal,
         uv,
         lightmap_uv,
+        colour,
         material_index,
         is_lightmapped,
         uniforms,
@@ -142,7 +165,12 @@ pub fn fragment(
         metallic_roughness_texture,
         emissive_texture,
         material_settings,
-        texture_sampler,
+        albedo_sampler,
+        occlusion_texture,
+        normal_sampler,
+        metallic_roughness_sampler,
+        emissive_sampler,
+        occlusion_sampler,
         0,
         front_facing,
         output,
@@ -155,6 +183,7 @@ pub fn fragment_alpha_blended(
     normal: Vec3,
     uv: Vec2,
     lightmap_uv: Vec2,
+    colour: Vec4,
     #[spirv(flat)] material_index: u32,
     #[spirv(flat)] is_lightmapped: u32,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
@@ -172,7 +201,12 @@ pub fn fragment_alpha_blended(
     #[spirv(descriptor_set = 1, binding = 2)] metallic_roughness_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 3)] emissive_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 4, uniform)] material_settings: &MaterialSettings,
-    #[spirv(descriptor_set = 1, binding = 5)] texture_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 5)] albedo_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 6)] occlusion_texture: &Image2D,
+    #[spirv(descriptor_set = 1, binding = 7)] normal_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 8)] metallic_roughness_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 9)] emissive_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 10)] occlusion_sampler: &Sampler,
     #[spirv(front_facing)] front_facing: bool,
     output: &mut Vec4,
 ) {
@@ -181,6 +215,7 @@ pub fn fragment_alpha_blended(
         normal,
         uv,
         lightmap_uv,
+        colour,
         material_index,
         is_lightmapped,
         uniforms,
@@ -198,7 +233,12 @@ pub fn fragment_alpha_blended(
         metallic_roughness_texture,
         emissive_texture,
         material_settings,
-        texture_sampler,
+        albedo_sampler,
+        occlusion_texture,
+        normal_sampler,
+        metallic_roughness_sampler,
+        emissive_sampler,
+        occlusion_sampler,
         0,
         front_facing,
         output,
@@ -211,6 +251,7 @@ pub fn fragment_alpha_clipped(
     normal: Vec3,
     uv: Vec2,
     lightmap_uv: Vec2,
+    colour: Vec4,
     #[spirv(flat)] material_index: u32,
     #[spirv(flat)] is_lightmapped: u32,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
@@ -228,7 +269,12 @@ pub fn fragment_alpha_clipped(
     #[spirv(descriptor_set = 1, binding = 2)] metallic_roughness_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 3)] emissive_texture: &Image2D,
     #[spirv(descriptor_set = 1, binding = 4, uniform)] material_settings: &MaterialSettings,
-    #[spirv(descriptor_set = 1, binding = 5)] texture_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 5)] albedo_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 6)] occlusion_texture: &Image2D,
+    #[spirv(descriptor_set = 1, binding = 7)] normal_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 8)] metallic_roughness_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 9)] emissive_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 10)] occlusion_sampler: &Sampler,
     #[spirv(front_facing)] front_facing: bool,
     output: &mut Vec4,
 ) {
@@ -237,6 +283,7 @@ pub fn fragment_alpha_clipped(
         normal,
         uv,
         lightmap_uv,
+        colour,
         material_index,
         is_lightmapped,
         uniforms,
@@ -254,7 +301,12 @@ pub fn fragment_alpha_clipped(
         metallic_roughness_texture,
         emissive_texture,
         material_settings,
-        texture_sampler,
+        albedo_sampler,
+        occlusion_texture,
+        normal_sampler,
+        metallic_roughness_sampler,
+        emissive_sampler,
+        occlusion_sampler,
         0,
         front_facing,
         output,
@@ -300,15 +352,17 @@ pub fn tonemap(
 #[spirv(vertex)]
 pub fn depth_prepass_vertex(
     position: Vec3,
-    instance_translation_and_scale: Vec4,
-    instance_rotation: glam::Quat,
+    instance_x_row: Vec4,
+    instance_y_row: Vec4,
+    instance_z_row: Vec4,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] uniforms: &Uniforms,
     #[spirv(position)] builtin_pos: &mut Vec4,
 ) {
     super::depth_prepass_vertex(
         position,
-        instance_translation_and_scale,
-        instance_rotation,
+        instance_x_row,
+        instance_y_row,
+        instance_z_row,
         uniforms,
         builtin_pos,
         0,
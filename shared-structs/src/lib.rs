@@ -1,7 +1,7 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use core::ops::{BitOr, BitOrAssign, Mul};
-use glam::{Mat2, Mat4, Vec2, Vec3, Vec4};
+use core::ops::{Add, BitOr, BitOrAssign, Mul};
+use glam::{Mat2, Mat3, Mat4, Quat, Vec2, Vec3, Vec4};
 #[cfg(target_arch = "spirv")]
 use num_traits::Float;
 
@@ -237,26 +237,44 @@ impl From<Mat4> for FlatMat4 {
 #[repr(C)]
 pub struct MaterialSettings {
     pub base_color_factor: Vec4,
-    pub texture_transform_offset: Vec2,
-    pub texture_transform_scale: Vec2,
+    pub albedo_transform: TextureTransform,
+    pub metallic_roughness_transform: TextureTransform,
+    pub normal_transform: TextureTransform,
+    pub emissive_transform: TextureTransform,
+    pub occlusion_transform: TextureTransform,
     pub emissive_factor_x: f32,
     pub emissive_factor_y: f32,
     pub emissive_factor_z: f32,
-    pub texture_transform_rotation: f32,
     pub metallic_factor: f32,
     pub roughness_factor: f32,
     pub normal_map_scale: f32,
     pub binary_settings: BinaryMaterialSettings,
+    pub occlusion_strength: f32,
+    // Fragments of alpha-clipped materials with a lower alpha than this are discarded.
+    pub alpha_cutoff: f32,
+    // KHR_materials_ior
+    pub index_of_refraction: f32,
+    // KHR_materials_specular
+    pub specular_factor: f32,
+    pub specular_colour_factor_x: f32,
+    pub specular_colour_factor_y: f32,
+    pub specular_colour_factor_z: f32,
+    // KHR_materials_clearcoat
+    pub clearcoat_factor: f32,
+    pub clearcoat_roughness_factor: f32,
+    // KHR_materials_sheen
+    pub sheen_colour_factor_x: f32,
+    pub sheen_colour_factor_y: f32,
+    pub sheen_colour_factor_z: f32,
+    pub sheen_roughness_factor: f32,
+    // KHR_materials_transmission
+    pub transmission_factor: f32,
+    // Pad the struct to a multiple of 16 bytes, as it's aligned to the Vec4.
+    #[cfg(not(target_arch = "spirv"))]
+    pub _padding: [u32; 3],
 }
 
 impl MaterialSettings {
-    pub fn transform_uv(self, uv: Vec2) -> Vec2 {
-        self.texture_transform_offset
-            + (Mat2::from_angle(self.texture_transform_rotation)
-                * self.texture_transform_scale
-                * uv)
-    }
-
     pub fn emissive_factor(self) -> Vec3 {
         Vec3::new(
             self.emissive_factor_x,
@@ -265,6 +283,22 @@ impl MaterialSettings {
         )
     }
 
+    pub fn specular_colour_factor(self) -> Vec3 {
+        Vec3::new(
+            self.specular_colour_factor_x,
+            self.specular_colour_factor_y,
+            self.specular_colour_factor_z,
+        )
+    }
+
+    pub fn sheen_colour_factor(self) -> Vec3 {
+        Vec3::new(
+            self.sheen_colour_factor_x,
+            self.sheen_colour_factor_y,
+            self.sheen_colour_factor_z,
+        )
+    }
+
     pub fn default_unlit() -> Self {
         Self {
             base_color_factor: Vec4::ONE,
@@ -275,13 +309,65 @@ impl MaterialSettings {
             roughness_factor: 1.0,
             normal_map_scale: 1.0,
             binary_settings: BinaryMaterialSettings::UNLIT,
-            texture_transform_offset: Vec2::ZERO,
-            texture_transform_scale: Vec2::ONE,
-            texture_transform_rotation: 0.0,
+            albedo_transform: TextureTransform::IDENTITY,
+            metallic_roughness_transform: TextureTransform::IDENTITY,
+            normal_transform: TextureTransform::IDENTITY,
+            emissive_transform: TextureTransform::IDENTITY,
+            occlusion_transform: TextureTransform::IDENTITY,
+            occlusion_strength: 1.0,
+            alpha_cutoff: 0.5,
+            index_of_refraction: 1.5,
+            specular_factor: 1.0,
+            specular_colour_factor_x: 1.0,
+            specular_colour_factor_y: 1.0,
+            specular_colour_factor_z: 1.0,
+            clearcoat_factor: 0.0,
+            clearcoat_roughness_factor: 0.0,
+            sheen_colour_factor_x: 0.0,
+            sheen_colour_factor_y: 0.0,
+            sheen_colour_factor_z: 0.0,
+            sheen_roughness_factor: 0.0,
+            transmission_factor: 0.0,
+            #[cfg(not(target_arch = "spirv"))]
+            _padding: Default::default(),
         }
     }
 }
 
+// The `KHR_texture_transform` of a material texture, along with the texCoord set it uses.
+#[derive(Clone, Copy)]
+#[cfg_attr(
+    not(target_arch = "spirv"),
+    derive(Debug, bytemuck::Zeroable, bytemuck::Pod)
+)]
+#[repr(C)]
+pub struct TextureTransform {
+    pub offset: Vec2,
+    pub scale: Vec2,
+    pub rotation: f32,
+    // Either 0 or 1.
+    pub tex_coord: u32,
+    // Structs in uniform buffers are 16-byte aligned, so this padding needs to exist on the gpu
+    // side as well to keep the offsets of the following fields the same.
+    pub _padding: [u32; 2],
+}
+
+impl TextureTransform {
+    pub const IDENTITY: Self = Self {
+        offset: Vec2::ZERO,
+        scale: Vec2::ONE,
+        rotation: 0.0,
+        tex_coord: 0,
+        _padding: [0; 2],
+    };
+
+    pub fn transform_uv(self, uv: Vec2, second_uv: Vec2) -> Vec2 {
+        let uv = if self.tex_coord == 1 { second_uv } else { uv };
+
+        self.offset + (Mat2::from_angle(self.rotation) * self.scale * uv)
+    }
+}
+
 #[derive(Clone, Copy, Default)]
 #[cfg_attr(
     not(target_arch = "spirv"),
@@ -316,44 +402,131 @@ impl BitOrAssign for BinaryMaterialSettings {
     }
 }
 
+// The rows of a 3x4 affine transform matrix. Used for instance transforms and for joint
+// transforms that need a non-uniform scale, so that those can be represented.
 #[derive(Clone, Copy)]
 #[cfg_attr(
     not(target_arch = "spirv"),
     derive(Debug, bytemuck::Zeroable, bytemuck::Pod)
 )]
 #[repr(C)]
-pub struct JointTransform {
-    pub translation_and_scale: Vec4,
-    pub rotation: glam::Quat,
+pub struct AffineTransform {
+    pub x_row: Vec4,
+    pub y_row: Vec4,
+    pub z_row: Vec4,
 }
 
-impl JointTransform {
-    pub const MAX_COUNT: usize = 65536 / core::mem::size_of::<Self>();
+impl AffineTransform {
+    pub fn from_mat4(mat4: Mat4) -> Self {
+        let transposed = mat4.transpose();
 
-    pub fn new(translation: Vec3, scale: f32, rotation: glam::Quat) -> Self {
         Self {
-            translation_and_scale: translation.extend(scale),
-            rotation,
+            x_row: transposed.x_axis,
+            y_row: transposed.y_axis,
+            z_row: transposed.z_axis,
         }
     }
 
-    fn translation(&self) -> Vec3 {
-        self.translation_and_scale.truncate()
+    // Unpack a joint transform that was stored as a translation and uniform scale followed by a
+    // rotation.
+    pub fn from_packed_similarity(translation_and_scale: Vec4, rotation: Quat) -> Self {
+        let matrix = Mat3::from_quat(rotation) * translation_and_scale.w;
+
+        Self {
+            x_row: matrix.row(0).extend(translation_and_scale.x),
+            y_row: matrix.row(1).extend(translation_and_scale.y),
+            z_row: matrix.row(2).extend(translation_and_scale.z),
+        }
     }
 
-    fn scale(&self) -> f32 {
-        self.translation_and_scale.w
+    fn matrix3(&self) -> Mat3 {
+        Mat3::from_cols(
+            self.x_row.truncate(),
+            self.y_row.truncate(),
+            self.z_row.truncate(),
+        )
+        .transpose()
+    }
+
+    // Transform a normal by the inverse transpose of the matrix, using the cofactor matrix so
+    // that we don't need to calculate the inverse. The result needs to be normalized.
+    pub fn transform_normal(&self, normal: Vec3) -> Vec3 {
+        let matrix = self.matrix3();
+
+        let cofactor = Mat3::from_cols(
+            matrix.y_axis.cross(matrix.z_axis),
+            matrix.z_axis.cross(matrix.x_axis),
+            matrix.x_axis.cross(matrix.y_axis),
+        );
+
+        // The cofactor matrix is the inverse transpose multiplied by the determinant, so flip it
+        // back for negative scales.
+        let sign = if matrix.determinant() < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+
+        cofactor * normal * sign
     }
 }
 
-impl Mul<Vec3> for JointTransform {
+impl Mul<Vec3> for AffineTransform {
     type Output = Vec3;
 
     fn mul(self, vector: Vec3) -> Vec3 {
-        self.translation() + (self.scale() * (self.rotation * vector))
+        let vector = vector.extend(1.0);
+        Vec3::new(
+            self.x_row.dot(vector),
+            self.y_row.dot(vector),
+            self.z_row.dot(vector),
+        )
+    }
+}
+
+impl Mul<f32> for AffineTransform {
+    type Output = Self;
+
+    fn mul(self, scalar: f32) -> Self {
+        Self {
+            x_row: self.x_row * scalar,
+            y_row: self.y_row * scalar,
+            z_row: self.z_row * scalar,
+        }
     }
 }
 
+impl Add<AffineTransform> for AffineTransform {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            x_row: self.x_row + other.x_row,
+            y_row: self.y_row + other.y_row,
+            z_row: self.z_row + other.z_row,
+        }
+    }
+}
+
+// Joint transforms are stored in a uniform buffer of vec4s. Models whose joints only ever have
+// uniform scales pack each joint into two vec4s (translation and scale, then rotation), and
+// everything else uses the three rows of an `AffineTransform`.
+pub const MAX_JOINT_VEC4S: usize = 65536 / core::mem::size_of::<Vec4>();
+
+pub const MAX_JOINTS: usize = MAX_JOINT_VEC4S / 2;
+
+pub const MAX_AFFINE_JOINTS: usize = MAX_JOINT_VEC4S / 3;
+
+// Bits of an instance's flags.
+pub const INSTANCE_LIGHTMAPPED: u32 = 1 << 0;
+pub const INSTANCE_AFFINE_JOINTS: u32 = 1 << 1;
+
+// The morph target weights of all the instances in a joint buffer, as a uniform array of vec4s.
+pub const MAX_MORPH_WEIGHTS: usize = 65536 / core::mem::size_of::<f32>();
+
+// Morph target deltas are stored in rows of a 2D texture, as 1D textures can't be very large.
+pub const MORPH_TARGET_TEXTURE_WIDTH: u32 = 2048;
+
 pub type L1SphericalHarmonics = [Vec3; 4];
 
 pub fn spherical_harmonics_channel_vectors(harmonics: L1SphericalHarmonics) -> (Vec3, Vec3, Vec3) {
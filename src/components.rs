@@ -1,7 +1,10 @@
 use bevy_ecs::prelude::{Component, Entity};
+use futures::future::AbortHandle;
 use renderer_core::arc_swap::ArcSwapOption;
 use renderer_core::assets::models;
-use renderer_core::shared_structs::JointTransform;
+use renderer_core::glam::Vec4;
+use renderer_core::gltf_helpers::{Similarity, Transform};
+use renderer_core::shared_structs::{AffineTransform, MAX_JOINT_VEC4S, MAX_MORPH_WEIGHTS};
 use std::ops::Range;
 use std::sync::Arc;
 
@@ -11,14 +14,59 @@ pub struct Instance(pub renderer_core::Instance);
 #[derive(Component)]
 pub struct InstanceOf(pub Entity);
 
+// Add to an instance of a model to render it with one of the model's `KHR_materials_variants`
+// variants, by name. Unknown names use the default materials. Models with variant materials that
+// have a different alpha mode or double-sidedness to the default fail to load.
+#[derive(Component, Clone, Debug)]
+pub struct MaterialVariant(pub String);
+
+// A model that's being loaded, which can be shared between entities. The load is aborted when
+// this is dropped, so loads for entities that are despawned (or have their url changed) don't
+// keep running.
+pub struct PendingLoad<T> {
+    setter: ArcSwapOption<T>,
+    abort_handle: AbortHandle,
+}
+
+impl<T> PendingLoad<T> {
+    pub(crate) fn new(abort_handle: AbortHandle) -> Self {
+        Self {
+            setter: ArcSwapOption::empty(),
+            abort_handle,
+        }
+    }
+
+    // A pending load for a model that has already been loaded.
+    pub(crate) fn from_loaded(model: Arc<T>) -> Self {
+        Self {
+            setter: ArcSwapOption::new(Some(model)),
+            abort_handle: AbortHandle::new_pair().0,
+        }
+    }
+
+    pub(crate) fn store(&self, model: Arc<T>) {
+        self.setter.store(Some(model));
+    }
+
+    pub fn loaded(&self) -> Option<Arc<T>> {
+        self.setter.load_full()
+    }
+}
+
+impl<T> Drop for PendingLoad<T> {
+    fn drop(&mut self) {
+        self.abort_handle.abort();
+    }
+}
+
 #[derive(Component)]
-pub struct PendingModel(pub Arc<ArcSwapOption<models::Model>>);
+pub struct PendingModel(pub Arc<PendingLoad<models::Model>>);
 
 #[derive(Component)]
 pub struct Model(pub Arc<models::Model>);
 
 #[derive(Component)]
-pub struct PendingAnimatedModel(pub Arc<ArcSwapOption<models::AnimatedModel>>);
+pub struct PendingAnimatedModel(pub Arc<PendingLoad<models::AnimatedModel>>);
 
 #[derive(Component)]
 pub struct AnimatedModel(pub Arc<models::AnimatedModel>);
@@ -92,7 +140,16 @@ mod instance_ranges {
 
     #[derive(Debug)]
     pub struct Lod {
-        pub ranges: Vec<Range<u32>>,
+        // The range of `runs` for each primitive.
+        pub ranges: Vec<Range<usize>>,
+        pub runs: Vec<MaterialRun>,
+    }
+
+    // A range of instances that use the same material.
+    #[derive(Debug)]
+    pub struct MaterialRun {
+        pub material_index: usize,
+        pub instances: Range<u32>,
     }
 }
 
@@ -100,73 +157,243 @@ impl InstanceRanges {
     pub fn clear(&mut self) {
         for lod in &mut self.lods {
             lod.ranges.clear();
+            lod.runs.clear();
         }
     }
 
-    pub fn push(&mut self, lod: usize, range: Range<u32>) {
+    // Push the range of a primitive's instances, splitting it into runs of instances that use the
+    // same material. The instances should be sorted by material.
+    pub fn push(
+        &mut self,
+        lod: usize,
+        range: Range<u32>,
+        instances: &[renderer_core::GpuInstance],
+    ) {
         while lod >= self.lods.len() {
-            self.lods.push(instance_ranges::Lod { ranges: Vec::new() });
+            self.lods.push(instance_ranges::Lod {
+                ranges: Vec::new(),
+                runs: Vec::new(),
+            });
         }
-        self.lods[lod].ranges.push(range);
+
+        let lod = &mut self.lods[lod];
+        let runs_start = lod.runs.len();
+
+        for (instance_index, instance) in (range.start..range.end).zip(instances) {
+            let material_index = instance.material_index as usize;
+
+            if let Some(run) = lod.runs[runs_start..]
+                .last_mut()
+                .filter(|run| run.material_index == material_index)
+            {
+                run.instances.end = instance_index + 1;
+                continue;
+            }
+
+            lod.runs.push(instance_ranges::MaterialRun {
+                material_index,
+                instances: instance_index..instance_index + 1,
+            });
+        }
+
+        lod.ranges.push(runs_start..lod.runs.len());
     }
 }
 
+#[derive(Component, Clone, Debug)]
+pub enum LoadState {
+    Loading,
+    Ready,
+    Failed(Arc<anyhow::Error>),
+}
+
+// Progress of the geometry and texture loading for a model. This stays on the entity after the
+// model is ready, as textures continue to stream in.
+#[derive(Component)]
+pub struct ModelLoadProgress(pub Arc<models::LoadProgress>);
+
+// The priority used for loading a model, shared with its loads so that it can be updated as the
+// camera moves.
+#[derive(Component)]
+pub struct ModelLoadPriority(pub Arc<renderer_core::assets::load_scheduler::LoadPriority>);
+
+// Add to a model entity to load it before models with a lower priority. Models with the same
+// priority are loaded in order of their distance from the camera.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct LoadPriority(pub i32);
+
 #[derive(Component)]
 pub struct ModelUrl(pub url::Url);
 
 #[derive(Component)]
 pub struct AnimatedModelUrl(pub url::Url);
 
+// Add to a model entity to load a scene other than the glTF's default one. Only the nodes that
+// are reachable from the scene are loaded. Changing or removing this reloads the model.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ModelScene(pub models::Scene);
+
+// Add to a model entity to spawn an entity for each of the model's cameras and
+// `KHR_lights_punctual` lights once it has loaded. These get an `Instance` that is relative to the
+// model's origin and are despawned when the model is reloaded or removed. Animated models spawn
+// them at the rest pose of their nodes.
+#[derive(Component, Default)]
+pub struct SpawnSceneObjects;
+
+// The model entity that a camera or light was spawned from.
+#[derive(Component)]
+pub struct SceneObjectOf(pub Entity);
+
+#[derive(Component, Clone, Debug)]
+pub struct SceneCamera(pub models::Camera);
+
+#[derive(Component, Clone, Debug)]
+pub struct SceneLight(pub models::Light);
+
+// Add to an entity with a `file://` model url to reload the model whenever the gltf or any of the
+// buffers or images it loaded are modified. Modified images are loaded again instead of being
+// taken from the texture cache. Note that a `CachingHttpClient` will keep returning the old bytes
+// unless the url is removed from it.
+#[cfg(not(feature = "wasm"))]
+#[derive(Component, Default)]
+pub struct WatchModelFile {
+    pub(crate) modified: std::collections::HashMap<std::path::PathBuf, std::time::SystemTime>,
+}
+
 #[derive(Component, Debug)]
 pub struct JointsOffset(pub u32);
 
+#[derive(Component, Debug)]
+pub struct MorphWeightsOffset(pub u32);
+
 #[derive(Component)]
 pub struct JointBuffers {
     pub next_buffer: usize,
     pub buffers: Vec<JointBuffer>,
+    // Set for models without morph weights, so that their joint buffers don't each need a
+    // morph weights buffer of their own.
+    shared_morph_weights_buffer: Option<Arc<wgpu::Buffer>>,
 }
 
 impl JointBuffers {
     pub fn new(
         device: &wgpu::Device,
         bind_group_layouts: &renderer_core::BindGroupLayouts,
+        morph_target_texture: &wgpu::TextureView,
+        shared_morph_weights_buffer: Option<Arc<wgpu::Buffer>>,
     ) -> Self {
-        Self {
+        let mut joint_buffers = Self {
             next_buffer: 0,
-            buffers: vec![JointBuffer::new(device, bind_group_layouts)],
-        }
+            buffers: Vec::new(),
+            shared_morph_weights_buffer,
+        };
+
+        joint_buffers.push_buffer(device, bind_group_layouts, morph_target_texture);
+
+        joint_buffers
+    }
+
+    pub fn push_buffer(
+        &mut self,
+        device: &wgpu::Device,
+        bind_group_layouts: &renderer_core::BindGroupLayouts,
+        morph_target_texture: &wgpu::TextureView,
+    ) {
+        self.buffers.push(JointBuffer::new(
+            device,
+            bind_group_layouts,
+            morph_target_texture,
+            self.shared_morph_weights_buffer.clone(),
+        ));
     }
 }
 
+// The joints and morph weights of a batch of instances of an animated model.
 pub struct JointBuffer {
-    pub staging: arrayvec::ArrayVec<JointTransform, { JointTransform::MAX_COUNT }>,
+    pub staging: arrayvec::ArrayVec<Vec4, MAX_JOINT_VEC4S>,
     pub buffer: wgpu::Buffer,
+    pub morph_weights_staging: arrayvec::ArrayVec<f32, { MAX_MORPH_WEIGHTS }>,
+    pub morph_weights_buffer: Arc<wgpu::Buffer>,
     pub bind_group: wgpu::BindGroup,
 }
 
 impl JointBuffer {
+    // The shader always reads from a full-sized uniform buffer, so models without morph weights
+    // should pass in a shared buffer instead of creating one per joint buffer.
     pub fn new(
         device: &wgpu::Device,
         bind_group_layouts: &renderer_core::BindGroupLayouts,
+        morph_target_texture: &wgpu::TextureView,
+        morph_weights_buffer: Option<Arc<wgpu::Buffer>>,
     ) -> Self {
         let buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("joint buffer"),
-            size: std::mem::size_of::<[JointTransform; JointTransform::MAX_COUNT]>() as u64,
+            size: std::mem::size_of::<[Vec4; MAX_JOINT_VEC4S]>() as u64,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
+        let morph_weights_buffer = morph_weights_buffer
+            .unwrap_or_else(|| Arc::new(Self::create_morph_weights_buffer(device)));
+
         Self {
             staging: Default::default(),
+            morph_weights_staging: Default::default(),
             bind_group: device.create_bind_group(&wgpu::BindGroupDescriptor {
                 label: Some("joint buffer bind group"),
                 layout: &bind_group_layouts.joints,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: buffer.as_entire_binding(),
-                }],
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: morph_weights_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(morph_target_texture),
+                    },
+                ],
             }),
             buffer,
+            morph_weights_buffer,
+        }
+    }
+
+    pub fn create_morph_weights_buffer(device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("morph weights buffer"),
+            size: std::mem::size_of::<[f32; MAX_MORPH_WEIGHTS]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // See `shared_structs::MAX_JOINT_VEC4S` for how the joints are packed.
+    pub(crate) fn push_joint(
+        &mut self,
+        joint: Transform,
+        affine: bool,
+    ) -> Result<(), arrayvec::CapacityError> {
+        if affine {
+            let transform = AffineTransform::from_mat4(joint.as_mat4());
+
+            self.staging
+                .try_extend_from_slice(&[transform.x_row, transform.y_row, transform.z_row])
+        } else {
+            // This only happens if a non-uniform scale was set on a joint of a model that didn't
+            // have one when it was loaded, in which case the largest scale is used.
+            let similarity = match joint {
+                Transform::Similarity(similarity) => similarity,
+                Transform::Affine(_) => Similarity::new_from_mat4(joint.as_mat4()),
+            };
+
+            self.staging.try_extend_from_slice(&[
+                similarity.translation.extend(similarity.scale),
+                Vec4::from(similarity.rotation),
+            ])
         }
     }
 }
@@ -174,6 +401,11 @@ impl JointBuffer {
 #[derive(Component)]
 pub struct AnimationJoints(pub renderer_core::gltf_helpers::animation::AnimationJoints);
 
+// The morph target weights of an animated model instance. These are set by weight animations in
+// `Stage::BufferResetting`, but can also be set manually by systems that run after it.
+#[derive(Component)]
+pub struct MorphWeights(pub renderer_core::gltf_helpers::animation::MorphWeights);
+
 #[derive(Component)]
 pub struct AnimationState {
     pub time: f32,
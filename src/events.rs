@@ -0,0 +1,19 @@
+use bevy_ecs::prelude::{Entity, Event};
+use std::sync::Arc;
+
+// Sent whenever loading a model, IBL cubemap or set of lightvol textures fails.
+#[derive(Event, Clone, Debug)]
+pub struct AssetLoadError {
+    pub asset: FailedAsset,
+    pub error: Arc<anyhow::Error>,
+}
+
+// For models, `entity` is the entity that started the load. Every entity that was still waiting
+// on the load gets a `LoadState::Failed`.
+#[derive(Clone, Debug)]
+pub enum FailedAsset {
+    Model { entity: Entity, url: url::Url },
+    AnimatedModel { entity: Entity, url: url::Url },
+    IblCubemap(url::Url),
+    LightvolTextures,
+}
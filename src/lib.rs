@@ -9,6 +9,7 @@ use winit::{
 };
 
 pub mod components;
+pub mod events;
 pub mod resources;
 mod systems;
 
@@ -27,8 +28,9 @@ pub use renderer_core::{
 };
 
 use resources::{
-    Camera, CullingParams, Device, EventQueue, HttpClient, NewIblCubemap, NewLightvolTextures,
-    PipelineOptions, ProbesArrayInfo, Queue, SurfaceFrameView, TextureSettings, WindowChanges,
+    AssetCache, AssetLoadErrors, Camera, CompactGeometryBuffers, CullingParams, Device, EventQueue,
+    HttpClient, LoadScheduler, NewIblCubemap, NewLightvolTextures, PipelineOptions,
+    ProbesArrayInfo, Queue, SurfaceFrameView, TextureSettings, WindowChanges,
 };
 
 #[derive(SystemSet, Debug, PartialEq, Eq, Clone, Hash)]
@@ -49,6 +51,9 @@ pub enum Stage {
 pub struct XrPlugin<T: assets::HttpClient = SimpleHttpClient> {
     pub mode: Mode,
     pub http_client: T,
+    // How many model and texture loads can run at once. Defaults to
+    // `load_scheduler::DEFAULT_MAX_CONCURRENT_LOADS`.
+    pub max_concurrent_loads: usize,
 }
 
 impl<T: assets::HttpClient + Default> XrPlugin<T> {
@@ -56,6 +61,7 @@ impl<T: assets::HttpClient + Default> XrPlugin<T> {
         Self {
             mode,
             http_client: T::default(),
+            max_concurrent_loads: assets::load_scheduler::DEFAULT_MAX_CONCURRENT_LOADS,
         }
     }
 }
@@ -73,6 +79,14 @@ impl<T: assets::HttpClient> Plugin for XrPlugin<T> {
         app.insert_resource(CullingParams::default());
         app.insert_resource(ProbesArrayInfo::new(Vec3::ZERO, Vec3::ONE));
         app.insert_resource(NewLightvolTextures(None));
+        app.insert_resource(CompactGeometryBuffers::default());
+        app.insert_resource(AssetLoadErrors::default());
+        app.insert_resource(AssetCache::default());
+        app.insert_resource(LoadScheduler(assets::load_scheduler::LoadScheduler::new(
+            self.max_concurrent_loads,
+        )));
+
+        app.add_event::<events::AssetLoadError>();
 
         app.add_systems(
             bevy_app::Startup,
@@ -86,6 +100,11 @@ impl<T: assets::HttpClient> Plugin for XrPlugin<T> {
             (
                 systems::start_loading_models::<T>,
                 systems::finish_loading_models,
+                systems::spawn_scene_objects,
+                systems::update_load_priorities,
+                systems::unload_models,
+                systems::compact_geometry_buffers,
+                systems::report_asset_load_errors,
                 systems::update_ibl_resources::<T>,
                 systems::update_lightvol_textures::<T>,
                 systems::add_joints_to_instances,
@@ -93,6 +112,14 @@ impl<T: assets::HttpClient> Plugin for XrPlugin<T> {
                 .in_set(Stage::AssetLoading),
         );
 
+        #[cfg(not(feature = "wasm"))]
+        app.add_systems(
+            bevy_app::Update,
+            systems::watch_model_files
+                .in_set(Stage::AssetLoading)
+                .before(systems::start_loading_models::<T>),
+        );
+
         app.add_systems(
             bevy_app::Update,
             (
@@ -563,20 +590,25 @@ pub fn run_rendering_loop(mut app: bevy_app::App, initialised_state: Initialised
     }
 }
 
+fn byte_range_string(range: Range<usize>) -> String {
+    format!("bytes={}-{}", range.start, range.end - 1)
+}
+
 #[derive(Clone, Default)]
 pub struct SimpleHttpClient(surf::Client);
 
-impl renderer_core::assets::HttpClient for SimpleHttpClient {
-    fn fetch_bytes(
+impl SimpleHttpClient {
+    fn get(
         &self,
         url: &url::Url,
         byte_range: Option<Range<usize>>,
-    ) -> renderer_core::assets::HttpClientFuture {
-        fn byte_range_string(range: Range<usize>) -> String {
-            format!("bytes={}-{}", range.start, range.end - 1)
-        }
+        validators: &renderer_core::assets::Validators,
+        progress: Option<renderer_core::assets::FetchProgressCallback>,
+    ) -> renderer_core::assets::HttpClientFuture<renderer_core::assets::ConditionalResponse> {
+        use renderer_core::assets::{ConditionalResponse, Validators};
 
         let url = url.clone();
+        let validators = validators.clone();
 
         let client = self.0.clone();
 
@@ -591,16 +623,139 @@ impl renderer_core::assets::HttpClient for SimpleHttpClient {
                     .map_err(|err| anyhow::anyhow!("{}", err))?;
             }
 
-            log::debug!("Requesting {}", url);
+            if let Some(etag) = &validators.etag {
+                request_builder = request_builder
+                    .header("If-None-Match", etag.as_str())
+                    .map_err(|err| anyhow::anyhow!("{}", err))?;
+            }
 
-            let bytes = request_builder
-                .recv_bytes()
+            if let Some(last_modified) = &validators.last_modified {
+                request_builder = request_builder
+                    .header("If-Modified-Since", last_modified.as_str())
+                    .map_err(|err| anyhow::anyhow!("{}", err))?;
+            }
+
+            if validators.is_empty() {
+                log::debug!("Requesting {}", url);
+            } else {
+                log::debug!("Requesting {} (conditionally)", url);
+            }
+
+            let mut response = request_builder
                 .await
                 .map_err(|err| anyhow::anyhow!("{}", err))?;
 
+            if response.status() == surf::StatusCode::NotModified {
+                log::debug!("{} has not been modified", url);
+                return Ok(ConditionalResponse::NotModified);
+            }
+
+            let header = |name| {
+                response
+                    .header(name)
+                    .map(|values| values.last().as_str().to_string())
+            };
+
+            let validators = Validators {
+                etag: header("ETag"),
+                last_modified: header("Last-Modified"),
+            };
+
+            let bytes = read_body(&mut response, progress.as_ref()).await?;
+
             log::debug!("Got bytes for {}: {}", url, bytes.len());
 
-            Ok(bytes)
+            Ok(ConditionalResponse::Modified { bytes, validators })
+        })
+    }
+
+    fn get_bytes(
+        &self,
+        url: &url::Url,
+        byte_range: Option<Range<usize>>,
+        progress: Option<renderer_core::assets::FetchProgressCallback>,
+    ) -> renderer_core::assets::HttpClientFuture {
+        use renderer_core::assets::ConditionalResponse;
+
+        let future = self.get(url, byte_range, &Default::default(), progress);
+
+        Box::pin(async move {
+            match future.await? {
+                ConditionalResponse::Modified { bytes, .. } => Ok(bytes),
+                ConditionalResponse::NotModified => Err(anyhow::anyhow!(
+                    "Got a not-modified response to an unconditional request"
+                )),
+            }
         })
     }
 }
+
+// Read a response in chunks so that the progress can be reported as it arrives.
+async fn read_body(
+    response: &mut surf::Response,
+    progress: Option<&renderer_core::assets::FetchProgressCallback>,
+) -> anyhow::Result<Vec<u8>> {
+    use futures::AsyncReadExt;
+    use renderer_core::assets::FetchProgress;
+
+    let report = |event| {
+        if let Some(progress) = progress {
+            progress(event);
+        }
+    };
+
+    let content_length = response.len();
+
+    if let Some(content_length) = content_length {
+        report(FetchProgress::ContentLength(content_length as u64));
+    }
+
+    let mut bytes = Vec::with_capacity(content_length.unwrap_or(0));
+    let mut chunk = vec![0; 64 * 1024];
+
+    loop {
+        let read = response.read(&mut chunk).await?;
+
+        if read == 0 {
+            break;
+        }
+
+        bytes.extend_from_slice(&chunk[..read]);
+        report(FetchProgress::Chunk(read as u64));
+    }
+
+    if content_length.is_none() {
+        report(FetchProgress::ContentLength(bytes.len() as u64));
+    }
+
+    Ok(bytes)
+}
+
+impl renderer_core::assets::HttpClient for SimpleHttpClient {
+    fn fetch_bytes(
+        &self,
+        url: &url::Url,
+        byte_range: Option<Range<usize>>,
+    ) -> renderer_core::assets::HttpClientFuture {
+        self.get_bytes(url, byte_range, None)
+    }
+
+    fn fetch_bytes_with_progress(
+        &self,
+        url: &url::Url,
+        byte_range: Option<Range<usize>>,
+        progress: renderer_core::assets::FetchProgressCallback,
+    ) -> renderer_core::assets::HttpClientFuture {
+        self.get_bytes(url, byte_range, Some(progress))
+    }
+
+    fn fetch_bytes_if_modified(
+        &self,
+        url: &url::Url,
+        byte_range: Option<Range<usize>>,
+        validators: &renderer_core::assets::Validators,
+        progress: Option<renderer_core::assets::FetchProgressCallback>,
+    ) -> renderer_core::assets::HttpClientFuture<renderer_core::assets::ConditionalResponse> {
+        self.get(url, byte_range, validators, progress)
+    }
+}
@@ -1,13 +1,16 @@
+use crate::components::PendingLoad;
 use bevy_ecs::system::Resource;
 use renderer_core::{
-    assets::textures,
+    assets::{load_scheduler, models, textures},
     culling::{BoundingSphereCullingParams, CullingFrustum},
     glam::{Mat4, Quat, Vec3},
     instance::ParticleInstance,
     GpuInstance, LineVertex, MutableBindGroup,
 };
-use std::sync::Arc;
+use std::collections::HashMap;
 use std::sync::atomic::AtomicU32;
+use std::sync::Arc;
+use std::sync::Weak;
 
 #[derive(Resource)]
 pub struct Device(pub Arc<wgpu::Device>);
@@ -48,7 +51,7 @@ impl MainBindGroup {
     pub(crate) fn new(inner: Arc<MutableBindGroup>) -> Self {
         Self {
             inner,
-            lightvol_z_layers: Arc::new(AtomicU32::new(1))
+            lightvol_z_layers: Arc::new(AtomicU32::new(1)),
         }
     }
 }
@@ -59,6 +62,241 @@ pub struct IndexBuffer(pub Arc<renderer_core::IndexBuffer>);
 pub struct VertexBuffers(pub Arc<renderer_core::VertexBuffers>);
 #[derive(Resource)]
 pub struct AnimatedVertexBuffers(pub Arc<renderer_core::AnimatedVertexBuffers>);
+// Shared by all the animated models without morph targets.
+#[derive(Resource)]
+pub(crate) struct EmptyMorphTargetTexture(pub(crate) Arc<renderer_core::Texture>);
+// Shared by the joint buffers of all the animated models without morph weights.
+#[derive(Resource)]
+pub(crate) struct EmptyMorphWeightsBuffer(pub(crate) Arc<wgpu::Buffer>);
+
+// Errors from asset loading futures, waiting to be sent as events.
+#[derive(Default, Resource)]
+pub(crate) struct AssetLoadErrors(
+    pub(crate) Arc<parking_lot::Mutex<Vec<(crate::events::AssetLoadError, FailedLoad)>>>,
+);
+
+// The load that an error came from. Loads are shared between entities and an entity can have
+// started a different load by the time the error arrives, so this is what decides which entities
+// get marked as failed.
+pub(crate) enum FailedLoad {
+    Model(Weak<PendingLoad<models::Model>>),
+    AnimatedModel(Weak<PendingLoad<models::AnimatedModel>>),
+    Other,
+}
+
+// Models and textures that are shared between everything that uses the same url. Only weak
+// references to loaded assets are kept, so they get freed when the last user is gone.
+#[derive(Default, Resource)]
+pub(crate) struct AssetCache {
+    pub(crate) models: ModelCache<models::Model>,
+    pub(crate) animated_models: ModelCache<models::AnimatedModel>,
+    pub(crate) textures: models::TextureCache,
+    // The scenes of every url that a model has been loaded from.
+    pub(crate) scene_indices: HashMap<url::Url, models::SceneIndices>,
+}
+
+impl AssetCache {
+    // Models are cached by their url and the scene that was loaded from them. Once a model has
+    // been loaded from a url, the scene is resolved to an index so that asking for the same scene
+    // by a different name or index shares the cached model.
+    pub(crate) fn model_key(&self, url: &url::Url, scene: &models::Scene) -> ModelKey {
+        let scene = self
+            .scene_indices
+            .get(url)
+            .and_then(|scene_indices| scene_indices.resolve(scene))
+            .unwrap_or_else(|| scene.clone());
+
+        (url.clone(), scene)
+    }
+
+    // Forget the scenes of urls that no cached model is from anymore. The model caches drop their
+    // dead entries when a load is started, so this should be called afterwards.
+    pub(crate) fn prune_scene_indices(&mut self) {
+        let models = &self.models;
+        let animated_models = &self.animated_models;
+
+        self.scene_indices
+            .retain(|url, _| models.contains_url(url) || animated_models.contains_url(url));
+    }
+}
+
+pub(crate) type ModelKey = (url::Url, models::Scene);
+
+pub(crate) struct ModelCache<T> {
+    entries: HashMap<ModelKey, ModelCacheEntry<T>>,
+}
+
+impl<T> Default for ModelCache<T> {
+    fn default() -> Self {
+        Self {
+            entries: Default::default(),
+        }
+    }
+}
+
+// The progress and priority of a load, which are shared by all the entities using it.
+#[derive(Clone, Default)]
+pub(crate) struct LoadHandles {
+    pub(crate) progress: Arc<models::LoadProgress>,
+    pub(crate) priority: Arc<load_scheduler::LoadPriority>,
+}
+
+struct ModelCacheEntry<T> {
+    // Set while the model is loading.
+    pending_load: Weak<PendingLoad<T>>,
+    // Set once the model has been picked up by an entity.
+    model: Weak<T>,
+    handles: LoadHandles,
+}
+
+impl<T> ModelCacheEntry<T> {
+    fn is_alive(&self) -> bool {
+        self.pending_load.strong_count() > 0 || self.model.strong_count() > 0
+    }
+}
+
+impl<T> ModelCache<T> {
+    // Get the pending load for the model at a url, along with its progress and priority. If the
+    // model has already loaded then the pending load is already filled.
+    pub(crate) fn get(&self, key: &ModelKey) -> Option<(Arc<PendingLoad<T>>, LoadHandles)> {
+        let entry = self.entries.get(key)?;
+
+        let pending_load = match entry.model.upgrade() {
+            Some(model) => Arc::new(PendingLoad::from_loaded(model)),
+            None => entry.pending_load.upgrade()?,
+        };
+
+        Some((pending_load, entry.handles.clone()))
+    }
+
+    // Whether an entity's model or pending load is the cached one for a url. Used to tell when a
+    // model is being reloaded instead of loaded for the first time.
+    pub(crate) fn is_cached(
+        &self,
+        key: &ModelKey,
+        model: Option<&Arc<T>>,
+        pending_load: Option<&Arc<PendingLoad<T>>>,
+    ) -> bool {
+        let entry = match self.entries.get(key) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        let model_is_cached = model
+            .map(|model| std::ptr::eq(Arc::as_ptr(model), entry.model.as_ptr()))
+            .unwrap_or(false);
+
+        let pending_load_is_cached = pending_load
+            .map(|pending_load| {
+                std::ptr::eq(Arc::as_ptr(pending_load), entry.pending_load.as_ptr())
+            })
+            .unwrap_or(false);
+
+        model_is_cached || pending_load_is_cached
+    }
+
+    pub(crate) fn insert_pending(
+        &mut self,
+        key: ModelKey,
+        pending_load: &Arc<PendingLoad<T>>,
+        handles: &LoadHandles,
+    ) {
+        self.entries.retain(|_, entry| entry.is_alive());
+
+        self.entries.insert(
+            key,
+            ModelCacheEntry {
+                pending_load: Arc::downgrade(pending_load),
+                model: Weak::new(),
+                handles: handles.clone(),
+            },
+        );
+    }
+
+    pub(crate) fn insert_loaded(&mut self, key: &ModelKey, model: &Arc<T>) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.model = Arc::downgrade(model);
+        }
+    }
+
+    // Share an entry with another key, used for the key of the scene that a model resolved to.
+    // A different load that's still alive under the alias is kept.
+    pub(crate) fn insert_alias(&mut self, key: &ModelKey, alias: ModelKey) {
+        if self
+            .entries
+            .get(&alias)
+            .map(|entry| entry.is_alive())
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        if let Some(entry) = self.entries.get(key) {
+            let entry = ModelCacheEntry {
+                pending_load: entry.pending_load.clone(),
+                model: entry.model.clone(),
+                handles: entry.handles.clone(),
+            };
+
+            self.entries.insert(alias, entry);
+        }
+    }
+
+    fn contains_url(&self, url: &url::Url) -> bool {
+        self.entries.keys().any(|(entry_url, _)| entry_url == url)
+    }
+
+    // Whether any model is still being loaded into the geometry buffers.
+    pub(crate) fn is_loading(&self) -> bool {
+        self.entries
+            .values()
+            .any(|entry| entry.pending_load.strong_count() > 0)
+    }
+
+    // Weak references stop `Arc::get_mut` from working, so they need to be forgotten while
+    // compacting and re-inserted afterwards.
+    pub(crate) fn forget_loaded(&mut self) {
+        for entry in self.entries.values_mut() {
+            entry.model = Weak::new();
+        }
+    }
+}
+
+// Set `requested` to compact the index and vertex buffers at the start of the next frame. The
+// compaction is put off until no models are loading, as their geometry could otherwise be written
+// to a buffer while it's being replaced. Only models used by a single entity can be moved, as
+// their ranges are patched through `Arc::get_mut`. The geometry of models shared between entities
+// stays where it is.
+#[derive(Resource)]
+pub struct CompactGeometryBuffers {
+    pub requested: bool,
+    // Shrink the buffers if less than this fraction of their capacity is in use.
+    pub shrink_threshold: f32,
+}
+
+impl Default for CompactGeometryBuffers {
+    fn default() -> Self {
+        Self {
+            requested: false,
+            shrink_threshold: 0.5,
+        }
+    }
+}
+
+// Limits how many model and texture loads run at once, starting the highest priority ones first.
+// The limit is set from `XrPlugin::max_concurrent_loads` and can be changed later with
+// `set_max_concurrent_loads`.
+#[derive(Resource)]
+pub struct LoadScheduler(pub load_scheduler::LoadScheduler);
+
+impl Default for LoadScheduler {
+    fn default() -> Self {
+        Self(load_scheduler::LoadScheduler::new(
+            load_scheduler::DEFAULT_MAX_CONCURRENT_LOADS,
+        ))
+    }
+}
+
 #[derive(Resource)]
 pub(crate) struct InstanceBuffer(pub(crate) renderer_core::VecGpuBuffer<GpuInstance>);
 
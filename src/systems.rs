@@ -1,27 +1,39 @@
 use crate::components::{
     AnimatedModel, AnimatedModelUrl, AnimationJoints, AnimationState, Instance, InstanceOf,
-    InstanceRanges, Instances, JointBuffer, JointBuffers, JointsOffset, Model, ModelUrl,
-    PendingAnimatedModel, PendingModel,
+    InstanceRanges, Instances, JointBuffer, JointBuffers, JointsOffset, LoadPriority, LoadState,
+    MaterialVariant, Model, ModelLoadPriority, ModelLoadProgress, ModelScene, ModelUrl,
+    MorphWeights, MorphWeightsOffset, PendingAnimatedModel, PendingLoad, PendingModel, SceneCamera,
+    SceneLight, SceneObjectOf, SpawnSceneObjects,
 };
+use crate::events::{AssetLoadError, FailedAsset};
 use crate::resources::{
-    AnimatedVertexBuffers, BindGroupLayouts, BoundingSphereParams, Camera, CompositeBindGroup,
-    CullingParams, Device, HttpClient, IndexBuffer, InstanceBuffer, IntermediateColorFramebuffer,
-    IntermediateDepthFramebuffer, LineBuffer, MainBindGroup, NewIblCubemap, NewLightvolTextures,
-    ParticleBuffer, PipelineOptions, Pipelines, ProbesArrayInfo, Queue, SurfaceFrameView,
-    TextureSettings, UniformBuffer, VertexBuffers,
+    AnimatedVertexBuffers, AssetCache, AssetLoadErrors, BindGroupLayouts, BoundingSphereParams,
+    Camera, CompactGeometryBuffers, CompositeBindGroup, CullingParams, Device,
+    EmptyMorphTargetTexture, EmptyMorphWeightsBuffer, FailedLoad, HttpClient, IndexBuffer,
+    InstanceBuffer, IntermediateColorFramebuffer, IntermediateDepthFramebuffer, LineBuffer,
+    LoadHandles, LoadScheduler, MainBindGroup, ModelCache, ModelKey, NewIblCubemap,
+    NewLightvolTextures, ParticleBuffer, PipelineOptions, Pipelines, ProbesArrayInfo, Queue,
+    SurfaceFrameView, TextureSettings, UniformBuffer, VertexBuffers,
 };
-use bevy_ecs::prelude::{Added, Commands, Entity, Local, Query, Res, ResMut, Without};
+use bevy_ecs::prelude::{
+    Added, Changed, Commands, Entity, EventWriter, Local, Or, Query, RemovedComponents, Res,
+    ResMut, With, Without,
+};
+use bevy_ecs::query::ReadOnlyWorldQuery;
 use renderer_core::{
-    arc_swap::ArcSwapOption,
     assets, bytemuck,
     culling::{BoundingSphereCullingParams, CullingFrustum},
     glam::Mat4,
-    shared_structs::{self, Settings},
-    spawn, GpuInstance, MutableBindGroup, Texture,
+    shared_structs::{self, AffineTransform, Settings},
+    spawn, spawn_abortable, GpuInstance, MutableBindGroup, Texture,
 };
-use std::sync::{Arc, atomic::Ordering};
+use std::collections::HashSet;
+use std::sync::{atomic::Ordering, Arc};
 use wgpu::util::DeviceExt;
 
+#[cfg(not(feature = "wasm"))]
+use crate::components::WatchModelFile;
+
 pub(crate) mod debugging;
 pub(crate) mod rendering;
 
@@ -59,6 +71,7 @@ pub(crate) fn clear_joint_buffers(mut query: Query<&mut JointBuffers>) {
 
         for joint_buffer in &mut joint_buffers.buffers {
             joint_buffer.staging.clear();
+            joint_buffer.morph_weights_staging.clear();
         }
     })
 }
@@ -107,16 +120,21 @@ pub(crate) fn progress_animation_times(
 }
 
 pub(crate) fn sample_animations(
-    mut instance_query: Query<(&InstanceOf, &mut AnimationJoints, &AnimationState)>,
+    mut instance_query: Query<(
+        &InstanceOf,
+        &mut AnimationJoints,
+        &mut MorphWeights,
+        &AnimationState,
+    )>,
     model_query: Query<&AnimatedModel>,
 ) {
-    instance_query.for_each_mut(|(instance_of, mut animation_joints, animation_state)| {
+    instance_query.for_each_mut(|(instance_of, mut joints, mut weights, animation_state)| {
         match model_query.get(instance_of.0) {
             Ok(animated_model) => {
                 let animations = &animated_model.0.animation_data.animations;
 
                 if let Some(animation) = animations.get(animation_state.animation_index) {
-                    animation.animate(&mut animation_joints.0, animation_state.time);
+                    animation.animate(&mut joints.0, &mut weights.0, animation_state.time);
                 }
             }
             Err(error) => {
@@ -134,38 +152,79 @@ pub(crate) fn upload_joint_buffers(query: Query<&JointBuffers>, queue: Res<Queue
                 0,
                 bytemuck::cast_slice(&joint_buffer.staging),
             );
+            // Models without morph weights share an empty buffer.
+            if !joint_buffer.morph_weights_staging.is_empty() {
+                queue.0.write_buffer(
+                    &joint_buffer.morph_weights_buffer,
+                    0,
+                    bytemuck::cast_slice(&joint_buffer.morph_weights_staging),
+                );
+            }
         }
     })
 }
 
 pub(crate) fn push_joints(
-    mut instance_query: Query<(Entity, &InstanceOf, &mut AnimationJoints)>,
+    mut instance_query: Query<(Entity, &InstanceOf, &mut AnimationJoints, &MorphWeights)>,
     mut model_query: Query<(&AnimatedModel, &mut JointBuffers)>,
     device: Res<Device>,
     bind_group_layouts: Res<BindGroupLayouts>,
     mut commands: Commands,
 ) {
-    instance_query.for_each_mut(|(entity, instance_of, mut animation_joints)| {
+    instance_query.for_each_mut(|(entity, instance_of, mut animation_joints, morph_weights)| {
         match model_query.get_mut(instance_of.0) {
             Ok((animated_model, mut joint_buffers)) => {
-                if joint_buffers.buffers[joint_buffers.next_buffer]
-                    .staging
-                    .remaining_capacity()
-                    < animated_model.0.num_joints() as usize
-                {
+                let num_joints = animated_model.0.num_joints() as usize;
+                let affine_joints = animated_model.0.animation_data.affine_joints;
+                let num_joint_vec4s = num_joints * if affine_joints { 3 } else { 2 };
+
+                // Move on to the next buffer until both the joints and the morph weights fit.
+                loop {
+                    let next_buffer = joint_buffers.next_buffer;
+                    let joint_buffer = &mut joint_buffers.buffers[next_buffer];
+
+                    if joint_buffer.staging.remaining_capacity() >= num_joint_vec4s
+                        && joint_buffer
+                            .morph_weights_staging
+                            .try_extend_from_slice(morph_weights.0.as_slice())
+                            .is_ok()
+                    {
+                        break;
+                    }
+
+                    // Even an empty buffer is too small, so trying another one won't help.
+                    if joint_buffer.staging.is_empty()
+                        && joint_buffer.morph_weights_staging.is_empty()
+                    {
+                        log::warn!(
+                            "Animated model instance has {} joints and {} morph weights, which don't fit into a joint buffer",
+                            num_joints,
+                            morph_weights.0.len()
+                        );
+                        commands
+                            .entity(entity)
+                            .remove::<(JointsOffset, MorphWeightsOffset)>();
+                        return;
+                    }
+
                     joint_buffers.next_buffer += 1;
 
                     if joint_buffers.next_buffer >= joint_buffers.buffers.len() {
-                        joint_buffers
-                            .buffers
-                            .push(JointBuffer::new(&device.0, &bind_group_layouts.0));
+                        joint_buffers.push_buffer(
+                            &device.0,
+                            &bind_group_layouts.0,
+                            &animated_model.0.morph_target_texture.view,
+                        );
                     }
                 }
 
-                commands.entity(entity).insert(JointsOffset(
-                    joint_buffers.buffers[joint_buffers.next_buffer]
-                        .staging
-                        .len() as u32,
+                let joint_buffer = &joint_buffers.buffers[joint_buffers.next_buffer];
+
+                commands.entity(entity).insert((
+                    JointsOffset(joint_buffer.staging.len() as u32),
+                    MorphWeightsOffset(
+                        (joint_buffer.morph_weights_staging.len() - morph_weights.0.len()) as u32,
+                    ),
                 ));
 
                 'joint_loop: for joint in animation_joints
@@ -178,17 +237,12 @@ pub(crate) fn push_joints(
                         &animated_model.0.animation_data.inverse_bind_transforms,
                         &animated_model.0.animation_data.depth_first_nodes,
                     )
-                    .map(|joint| {
-                        shared_structs::JointTransform::new(
-                            joint.translation,
-                            joint.scale,
-                            joint.rotation,
-                        )
-                    })
                 {
                     let next_buffer = joint_buffers.next_buffer;
 
-                    if let Err(error) = joint_buffers.buffers[next_buffer].staging.try_push(joint) {
+                    if let Err(error) =
+                        joint_buffers.buffers[next_buffer].push_joint(joint, affine_joints)
+                    {
                         log::warn!("Got an error when pushing joints: {}", error);
                         break 'joint_loop;
                     }
@@ -205,122 +259,166 @@ pub(crate) fn push_entity_instances(
     camera: Res<Camera>,
     culling_params: Res<CullingParams>,
     surface_frame_view: Option<Res<SurfaceFrameView>>,
-    mut instance_query: Query<(&InstanceOf, &Instance, Option<&JointsOffset>)>,
+    mut instance_query: Query<(
+        &InstanceOf,
+        &Instance,
+        Option<&JointsOffset>,
+        Option<&MorphWeightsOffset>,
+        Option<&MaterialVariant>,
+    )>,
     mut model_query: Query<(&mut Instances, Option<&Model>, Option<&AnimatedModel>)>,
 ) {
     let view_matrix = camera.view_matrix();
 
-    instance_query.for_each_mut(|(instance_of, instance, joints_offset)| {
+    for (instance_of, instance, joints_offset, weights_offset, variant) in instance_query.iter_mut()
+    {
         match model_query.get_mut(instance_of.0) {
             Ok((mut instances, model, animated_model)) => {
                 if let Some(model) = model {
                     instances.reserve_space(&model.0.primitives);
 
+                    let variant = variant.and_then(|variant| model.0.variant_index(&variant.0));
+
                     for (primitive_id, primitive) in model.0.primitives.iter().enumerate() {
-                        let primitive_transform = instance.0 * primitive.transform;
-
-                        // calculate the size of the min z frustum rectangle or something (I have removed min_z from both sides of the equation).
-                        // https://github.com/BabylonJS/Babylon.js/blob/d25bc29091d47f51bd2f0f98fb0f16d25517675f/packages/dev/core/src/Cameras/camera.ts#L149-L150
-                        // todo: research more.
-                        let screen_coverage = {
-                            let distance_to_camera =
-                                primitive_transform.translation.distance(camera.position);
-                            let bounding_sphere_radius =
-                                primitive.bounding_sphere.radius * primitive_transform.scale;
-                            let visible_radius = bounding_sphere_radius / distance_to_camera;
-                            let mesh_area = visible_radius * visible_radius * std::f32::consts::PI;
-                            // There isn't a way to get the window dimensions in WebXR mode so we just use default values.
-                            let (width, height) = surface_frame_view
-                                .as_ref()
-                                .map(|view| (view.width, view.height))
-                                .unwrap_or((1024, 1024));
-                            let aspect_ratio = width as f32 / height as f32;
-
-                            let screen_area = {
-                                let y = (59.0_f32.to_radians() / 2.0).tan();
-                                let x = y * aspect_ratio;
-                                x * y
+                        for &transform in &primitive.transforms {
+                            let primitive_transform = instance.0 * transform;
+
+                            // calculate the size of the min z frustum rectangle or something (I have removed min_z from both sides of the equation).
+                            // https://github.com/BabylonJS/Babylon.js/blob/d25bc29091d47f51bd2f0f98fb0f16d25517675f/packages/dev/core/src/Cameras/camera.ts#L149-L150
+                            // todo: research more.
+                            let screen_coverage = {
+                                let distance_to_camera =
+                                    primitive_transform.translation().distance(camera.position);
+                                let bounding_sphere_radius = primitive.bounding_sphere.radius
+                                    * primitive_transform.max_scale();
+                                let visible_radius = bounding_sphere_radius / distance_to_camera;
+                                let mesh_area =
+                                    visible_radius * visible_radius * std::f32::consts::PI;
+                                // There isn't a way to get the window dimensions in WebXR mode so we just use default values.
+                                let (width, height) = surface_frame_view
+                                    .as_ref()
+                                    .map(|view| (view.width, view.height))
+                                    .unwrap_or((1024, 1024));
+                                let aspect_ratio = width as f32 / height as f32;
+
+                                let screen_area = {
+                                    let y = (59.0_f32.to_radians() / 2.0).tan();
+                                    let x = y * aspect_ratio;
+                                    x * y
+                                };
+
+                                mesh_area / screen_area
                             };
 
-                            mesh_area / screen_area
-                        };
-
-                        // Chose the lod that the screen coverage fits into.
-                        let lod = match primitive.screen_coverages.binary_search_by(|value| {
-                            screen_coverage
-                                .partial_cmp(value)
-                                .unwrap_or(std::cmp::Ordering::Equal)
-                        }) {
-                            Ok(exact) => exact,
-                            Err(closest) => closest,
-                        };
-
-                        let mut passed_culling_check = match culling_params.bounding_sphere_params {
-                            BoundingSphereParams::SingleView(params) => {
-                                renderer_core::culling::test_bounding_sphere(
-                                    primitive.bounding_sphere,
-                                    primitive_transform,
-                                    params,
-                                )
+                            // Chose the lod that the screen coverage fits into.
+                            let lod = match primitive.screen_coverages.binary_search_by(|value| {
+                                screen_coverage
+                                    .partial_cmp(value)
+                                    .unwrap_or(std::cmp::Ordering::Equal)
+                            }) {
+                                Ok(exact) => exact,
+                                Err(closest) => closest,
+                            };
+
+                            let mut passed_culling_check =
+                                match culling_params.bounding_sphere_params {
+                                    BoundingSphereParams::SingleView(params) => {
+                                        renderer_core::culling::test_bounding_sphere(
+                                            primitive.bounding_sphere,
+                                            primitive_transform,
+                                            params,
+                                        )
+                                    }
+                                    BoundingSphereParams::Vr { left, right } => {
+                                        renderer_core::culling::test_bounding_sphere(
+                                            primitive.bounding_sphere,
+                                            primitive_transform,
+                                            left,
+                                        ) || renderer_core::culling::test_bounding_sphere(
+                                            primitive.bounding_sphere,
+                                            primitive_transform,
+                                            right,
+                                        )
+                                    }
+                                };
+
+                            if let Some(frustum) = culling_params.frustum {
+                                passed_culling_check &=
+                                    renderer_core::culling::test_using_separating_axis_theorem(
+                                        frustum,
+                                        view_matrix,
+                                        primitive_transform,
+                                        &primitive.bounding_box,
+                                    );
                             }
-                            BoundingSphereParams::Vr { left, right } => {
-                                renderer_core::culling::test_bounding_sphere(
-                                    primitive.bounding_sphere,
-                                    primitive_transform,
-                                    left,
-                                ) || renderer_core::culling::test_bounding_sphere(
-                                    primitive.bounding_sphere,
-                                    primitive_transform,
-                                    right,
-                                )
+
+                            if !passed_culling_check {
+                                continue;
                             }
-                        };
-
-                        if let Some(frustum) = culling_params.frustum {
-                            passed_culling_check &=
-                                renderer_core::culling::test_using_separating_axis_theorem(
-                                    frustum,
-                                    view_matrix,
-                                    primitive_transform,
-                                    &primitive.bounding_box,
-                                );
-                        }
 
-                        if !passed_culling_check {
-                            continue;
+                            let material_index =
+                                primitive.lods[lod].material_index_for_variant(variant);
+
+                            instances.insert(
+                                primitive_id,
+                                lod,
+                                GpuInstance {
+                                    transform: AffineTransform::from_mat4(
+                                        primitive_transform.as_mat4(),
+                                    ),
+                                    material_index: material_index as u32,
+                                    flags: if primitive.lods[lod].is_lightmapped {
+                                        shared_structs::INSTANCE_LIGHTMAPPED as u16
+                                    } else {
+                                        0
+                                    },
+                                    num_morph_targets: 0,
+                                    joints_offset: 0,
+                                    morph_weights_offset: 0,
+                                    morph_target_stride: 0,
+                                },
+                            );
                         }
-
-                        instances.insert(
-                            primitive_id,
-                            lod,
-                            GpuInstance {
-                                similarity: primitive_transform,
-                                joints_offset: joints_offset.map(|offset| offset.0).unwrap_or(0),
-                                material_index: primitive.lods[lod].material_index as u32,
-                                is_lightmapped: primitive.lods[lod].is_lightmapped as u32,
-                                _padding: Default::default(),
-                            },
-                        );
                     }
                 } else if let Some(animated_model) = animated_model {
                     instances.reserve_space(&animated_model.0.primitives);
 
+                    let variant =
+                        variant.and_then(|variant| animated_model.0.variant_index(&variant.0));
+
                     for (primitive_id, primitive) in animated_model.0.primitives.iter().enumerate()
                     {
-                        let primitive_transform = instance.0 * primitive.transform;
-
-                        // todo: culling for animated models.
-                        instances.insert(
-                            primitive_id,
-                            0,
-                            GpuInstance {
-                                similarity: primitive_transform,
-                                joints_offset: joints_offset.map(|offset| offset.0).unwrap_or(0),
-                                material_index: primitive.lods[0].material_index as u32,
-                                is_lightmapped: false as u32,
-                                _padding: Default::default(),
-                            },
-                        );
+                        for &transform in &primitive.transforms {
+                            let primitive_transform = instance.0 * transform;
+
+                            // todo: culling for animated models.
+                            instances.insert(
+                                primitive_id,
+                                0,
+                                GpuInstance {
+                                    transform: AffineTransform::from_mat4(
+                                        primitive_transform.as_mat4(),
+                                    ),
+                                    material_index: primitive.lods[0]
+                                        .material_index_for_variant(variant)
+                                        as u32,
+                                    flags: if animated_model.0.animation_data.affine_joints {
+                                        shared_structs::INSTANCE_AFFINE_JOINTS as u16
+                                    } else {
+                                        0
+                                    },
+                                    num_morph_targets: primitive.morph_targets.num_targets as u16,
+                                    joints_offset: joints_offset.map(|offset| offset.0).unwrap_or(0)
+                                        as u16,
+                                    morph_weights_offset: (weights_offset
+                                        .map(|offset| offset.0)
+                                        .unwrap_or(0)
+                                        + primitive.morph_targets.weights_offset)
+                                        as u16,
+                                    morph_target_stride: primitive.morph_targets.stride,
+                                },
+                            );
+                        }
                     }
                 }
             }
@@ -328,14 +426,14 @@ pub(crate) fn push_entity_instances(
                 log::warn!("Got an error when pushing an instance: {}", error);
             }
         }
-    })
+    }
 }
 
 pub(crate) fn upload_instances(
     device: Res<Device>,
     queue: Res<Queue>,
     mut instance_buffer: ResMut<InstanceBuffer>,
-    mut query: Query<(&Instances, &mut InstanceRanges)>,
+    mut query: Query<(&mut Instances, &mut InstanceRanges, Option<&Model>)>,
 ) {
     let mut command_encoder = device
         .0
@@ -343,20 +441,33 @@ pub(crate) fn upload_instances(
             label: Some("command encoder"),
         });
 
-    query.for_each_mut(|(instances, mut instance_ranges)| {
+    query.for_each_mut(|(mut instances, mut instance_ranges, model)| {
         instance_ranges.clear();
 
-        for primitives in instances.primitives.iter() {
-            for (lod_index, lod) in primitives.lods.iter().enumerate() {
-                instance_ranges.push(
-                    lod_index,
-                    instance_buffer.0.push(
-                        &lod.instances,
-                        &device.0,
-                        &queue.0,
-                        &mut command_encoder,
-                    ),
+        // Only models with material variants can have instances of a primitive that use different
+        // materials. The instances of animated models aren't sorted, as their joint buffers are
+        // split by instance order. Their instances with different variants just end up in more
+        // runs.
+        let has_variants = model
+            .map(|model| !model.0.variants.is_empty())
+            .unwrap_or(false);
+
+        for primitives in instances.primitives.iter_mut() {
+            for (lod_index, lod) in primitives.lods.iter_mut().enumerate() {
+                // Group the instances by material so that each material only needs one draw call.
+                if has_variants {
+                    lod.instances
+                        .sort_by_key(|instance| instance.material_index);
+                }
+
+                let range = instance_buffer.0.push(
+                    &lod.instances,
+                    &device.0,
+                    &queue.0,
+                    &mut command_encoder,
                 );
+
+                instance_ranges.push(lod_index, range, &lod.instances);
             }
         }
     });
@@ -561,6 +672,12 @@ pub(crate) fn allocate_bind_groups(
     commands.insert_resource(AnimatedVertexBuffers(Arc::new(
         renderer_core::AnimatedVertexBuffers::new(1024, device),
     )));
+    commands.insert_resource(EmptyMorphTargetTexture(Arc::new(
+        assets::models::create_empty_morph_target_texture(device, &queue.0),
+    )));
+    commands.insert_resource(EmptyMorphWeightsBuffer(Arc::new(
+        JointBuffer::create_morph_weights_buffer(device),
+    )));
 
     commands.insert_resource(InstanceBuffer(renderer_core::VecGpuBuffer::new(
         1,
@@ -600,6 +717,7 @@ pub(crate) fn update_lightvol_textures<T: assets::HttpClient>(
     mut new_lightvol_textures: ResMut<NewLightvolTextures>,
     main_bind_group: Res<MainBindGroup>,
     http_client: Res<HttpClient<T>>,
+    asset_load_errors: Res<AssetLoadErrors>,
 ) {
     let new_lightvol_textures = match new_lightvol_textures.0.take() {
         Some(new_lightvol_textures) => new_lightvol_textures,
@@ -625,7 +743,9 @@ pub(crate) fn update_lightvol_textures<T: assets::HttpClient>(
         settings: texture_settings.0.clone(),
     };
 
-    spawn(async move {
+    let asset_load_errors = asset_load_errors.0.clone();
+
+    let future = async move {
         use renderer_core::assets::textures::load_ktx2_async;
 
         let lightvol_future = futures::future::join4(
@@ -717,6 +837,17 @@ pub(crate) fn update_lightvol_textures<T: assets::HttpClient>(
         );
 
         Ok(())
+    };
+
+    spawn(async move {
+        future.await.map_err(|error| {
+            report_asset_load_error(
+                &asset_load_errors,
+                FailedAsset::LightvolTextures,
+                FailedLoad::Other,
+                error,
+            )
+        })
     });
 }
 
@@ -730,6 +861,7 @@ pub(crate) fn update_ibl_resources<T: assets::HttpClient>(
     mut new_ibl_cubemap: ResMut<NewIblCubemap>,
     main_bind_group: Res<MainBindGroup>,
     http_client: Res<HttpClient<T>>,
+    asset_load_errors: Res<AssetLoadErrors>,
 ) {
     let new_ibl_cubemap = match new_ibl_cubemap.0.take() {
         Some(new_ibl_cubemap) => new_ibl_cubemap,
@@ -752,6 +884,8 @@ pub(crate) fn update_ibl_resources<T: assets::HttpClient>(
         settings: texture_settings.0.clone(),
     };
 
+    let asset_load_errors = asset_load_errors.0.clone();
+
     spawn(async move {
         match renderer_core::assets::textures::load_ibl_cubemap(
             textures_context.clone(),
@@ -770,10 +904,14 @@ pub(crate) fn update_ibl_resources<T: assets::HttpClient>(
 
                 Ok(())
             }
-            Err(error) => Err(anyhow::anyhow!(
-                "Error file loading ibl cubemap {}: {}",
-                new_ibl_cubemap,
-                error
+            Err(error) => Err(report_asset_load_error(
+                &asset_load_errors,
+                FailedAsset::IblCubemap(new_ibl_cubemap.clone()),
+                FailedLoad::Other,
+                error.context(format!(
+                    "Error file loading ibl cubemap {}",
+                    new_ibl_cubemap
+                )),
             )),
         }
     });
@@ -865,7 +1003,7 @@ pub(crate) fn update_desktop_uniform_buffers(
 struct ViewData {
     projection: Mat4,
     view: Mat4,
-    instance: renderer_core::Instance,
+    instance: renderer_core::gltf_helpers::Similarity,
 }
 
 #[cfg(feature = "webgl")]
@@ -988,10 +1126,41 @@ pub(crate) fn update_webxr_uniform_buffers(
     };
 }
 
+// Changing the url of an already-loaded model starts loading the new one. The old model keeps
+// being rendered until the new one replaces it in `finish_loading_models`, and any in-progress
+// load for the old url is cancelled once nothing references it.
+//
+// Models that are already loaded or loading for another entity are shared via the asset cache,
+// unless the entity's current model is the cached one, in which case it gets reloaded. Changing
+// the scene of a model (including removing it, which goes back to the default scene) is handled
+// the same way as changing its url.
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn start_loading_models<T: assets::HttpClient>(
-    static_models: Query<(Entity, &ModelUrl), Added<ModelUrl>>,
-    animated_models: Query<(Entity, &AnimatedModelUrl), Added<AnimatedModelUrl>>,
+    (changed_static_models, static_models): (
+        Query<Entity, (With<ModelUrl>, Or<(Changed<ModelUrl>, Changed<ModelScene>)>)>,
+        Query<(
+            &ModelUrl,
+            Option<&ModelScene>,
+            Option<&Model>,
+            Option<&PendingModel>,
+        )>,
+    ),
+    (changed_animated_models, animated_models): (
+        Query<
+            Entity,
+            (
+                With<AnimatedModelUrl>,
+                Or<(Changed<AnimatedModelUrl>, Changed<ModelScene>)>,
+            ),
+        >,
+        Query<(
+            &AnimatedModelUrl,
+            Option<&ModelScene>,
+            Option<&AnimatedModel>,
+            Option<&PendingAnimatedModel>,
+        )>,
+    ),
+    mut removed_scenes: RemovedComponents<ModelScene>,
     device: Res<Device>,
     queue: Res<Queue>,
     pipelines: Res<Pipelines>,
@@ -1001,27 +1170,74 @@ pub(crate) fn start_loading_models<T: assets::HttpClient>(
         Res<VertexBuffers>,
         Res<AnimatedVertexBuffers>,
     ),
+    empty_morph_target_texture: Res<EmptyMorphTargetTexture>,
     texture_settings: Res<TextureSettings>,
     http_client: Res<HttpClient<T>>,
+    asset_load_errors: Res<AssetLoadErrors>,
+    mut asset_cache: ResMut<AssetCache>,
+    load_scheduler: Res<LoadScheduler>,
     mut commands: Commands,
 ) {
     let device = &device.0;
     let queue = &queue.0;
 
-    static_models.for_each(|(entity, url)| {
-        let url = url.0.clone();
+    let removed_scenes: HashSet<Entity> = removed_scenes.iter().collect();
+
+    for entity in entities_to_load(&changed_static_models, &removed_scenes) {
+        let (url, scene, model, pending_model) = match static_models.get(entity) {
+            Ok(components) => components,
+            Err(_) => continue,
+        };
+
+        let key = model_key(&asset_cache, &url.0, scene);
+
+        let reloading = asset_cache.models.is_cached(
+            &key,
+            model.map(|model| &model.0),
+            pending_model.map(|pending_model| &pending_model.0),
+        );
+
+        if !reloading {
+            if let Some((pending_load, handles)) = asset_cache.models.get(&key) {
+                commands
+                    .entity(entity)
+                    .insert(PendingModel(pending_load))
+                    .insert(LoadState::Loading)
+                    .insert(ModelLoadProgress(handles.progress))
+                    .insert(ModelLoadPriority(handles.priority));
+                continue;
+            }
+        }
+
+        let (url, scene) = key.clone();
         let vertex_buffers = vertex_buffers.0.clone();
         let animated_vertex_buffers = animated_vertex_buffers.0.clone();
         let index_buffer = index_buffer.0.clone();
         let texture_settings = texture_settings.0.clone();
 
-        let model_setter = Arc::new(ArcSwapOption::empty());
+        let (abort_handle, abort_registration) = futures::future::AbortHandle::new_pair();
+        let pending_load = Arc::new(PendingLoad::new(abort_handle));
+        let handles = LoadHandles::default();
 
         commands
             .entity(entity)
-            .insert(PendingModel(model_setter.clone()));
+            .insert(PendingModel(pending_load.clone()))
+            .insert(LoadState::Loading)
+            .insert(ModelLoadProgress(handles.progress.clone()))
+            .insert(ModelLoadPriority(handles.priority.clone()));
+
+        asset_cache
+            .models
+            .insert_pending(key, &pending_load, &handles);
+        asset_cache.prune_scene_indices();
 
-        spawn({
+        // Only hold a weak reference so that the load gets aborted when the last entity waiting
+        // on it is gone.
+        let pending_load = Arc::downgrade(&pending_load);
+
+        let asset_load_errors = asset_load_errors.0.clone();
+
+        let future = {
             let device = device.clone();
             let queue = queue.clone();
             let bind_group_layouts = bind_group_layouts.0.clone();
@@ -1037,41 +1253,99 @@ pub(crate) fn start_loading_models<T: assets::HttpClient>(
                 animated_vertex_buffers,
                 pipelines,
                 texture_settings,
+                texture_cache: asset_cache.textures.clone(),
+                progress: handles.progress,
+                load_scheduler: load_scheduler.0.clone(),
+                load_priority: handles.priority,
+                empty_morph_target_texture: empty_morph_target_texture.0.clone(),
             };
 
             async move {
-                let result = renderer_core::assets::models::Model::load(&context, &url).await;
+                let result =
+                    renderer_core::assets::models::Model::load(&context, &url, &scene).await;
 
                 match result {
                     Ok(model) => {
-                        model_setter.store(Some(Arc::new(model)));
+                        // If the load was no longer wanted then dropping the model here frees
+                        // its geometry.
+                        if let Some(pending_load) = pending_load.upgrade() {
+                            pending_load.store(Arc::new(model));
+                        }
 
                         Ok(())
                     }
-                    Err(error) => Err(anyhow::anyhow!(
-                        "Got an error while trying to load a model from '{}': {}",
-                        url,
-                        error
+                    Err(error) => Err(report_asset_load_error(
+                        &asset_load_errors,
+                        FailedAsset::Model {
+                            entity,
+                            url: url.clone(),
+                        },
+                        FailedLoad::Model(pending_load),
+                        error.context(format!(
+                            "Got an error while trying to load a model from '{}'",
+                            url
+                        )),
                     )),
                 }
             }
-        });
-    });
+        };
 
-    animated_models.for_each(|(entity, url)| {
-        let url = url.0.clone();
+        spawn_abortable(future, abort_registration);
+    }
+
+    for entity in entities_to_load(&changed_animated_models, &removed_scenes) {
+        let (url, scene, model, pending_model) = match animated_models.get(entity) {
+            Ok(components) => components,
+            Err(_) => continue,
+        };
+
+        let key = model_key(&asset_cache, &url.0, scene);
+
+        let reloading = asset_cache.animated_models.is_cached(
+            &key,
+            model.map(|model| &model.0),
+            pending_model.map(|pending_model| &pending_model.0),
+        );
+
+        if !reloading {
+            if let Some((pending_load, handles)) = asset_cache.animated_models.get(&key) {
+                commands
+                    .entity(entity)
+                    .insert(PendingAnimatedModel(pending_load))
+                    .insert(LoadState::Loading)
+                    .insert(ModelLoadProgress(handles.progress))
+                    .insert(ModelLoadPriority(handles.priority));
+                continue;
+            }
+        }
+
+        let (url, scene) = key.clone();
         let vertex_buffers = vertex_buffers.0.clone();
         let animated_vertex_buffers = animated_vertex_buffers.0.clone();
         let index_buffer = index_buffer.0.clone();
         let texture_settings = texture_settings.0.clone();
 
-        let model_setter = Arc::new(ArcSwapOption::empty());
+        let (abort_handle, abort_registration) = futures::future::AbortHandle::new_pair();
+        let pending_load = Arc::new(PendingLoad::new(abort_handle));
+        let handles = LoadHandles::default();
 
         commands
             .entity(entity)
-            .insert(PendingAnimatedModel(model_setter.clone()));
+            .insert(PendingAnimatedModel(pending_load.clone()))
+            .insert(LoadState::Loading)
+            .insert(ModelLoadProgress(handles.progress.clone()))
+            .insert(ModelLoadPriority(handles.priority.clone()));
+
+        asset_cache
+            .animated_models
+            .insert_pending(key, &pending_load, &handles);
+        asset_cache.prune_scene_indices();
+
+        let pending_load = Arc::downgrade(&pending_load);
 
-        spawn({
+        let asset_load_errors = asset_load_errors.0.clone();
+
+        let future = {
             let device = device.clone();
             let queue = queue.clone();
             let bind_group_layouts = bind_group_layouts.0.clone();
@@ -1087,51 +1361,576 @@ pub(crate) fn start_loading_models<T: assets::HttpClient>(
                 animated_vertex_buffers,
                 pipelines,
                 texture_settings,
+                texture_cache: asset_cache.textures.clone(),
+                progress: handles.progress,
+                load_scheduler: load_scheduler.0.clone(),
+                load_priority: handles.priority,
+                empty_morph_target_texture: empty_morph_target_texture.0.clone(),
             };
 
             async move {
                 let result =
-                    renderer_core::assets::models::AnimatedModel::load(&context, &url).await;
+                    renderer_core::assets::models::AnimatedModel::load(&context, &url, &scene)
+                        .await;
 
                 match result {
                     Ok(model) => {
-                        model_setter.store(Some(Arc::new(model)));
+                        if let Some(pending_load) = pending_load.upgrade() {
+                            pending_load.store(Arc::new(model));
+                        }
                         Ok(())
                     }
-                    Err(error) => Err(anyhow::anyhow!(
-                        "Got an error while trying to load a model from '{}': {}",
-                        url,
-                        error
+                    Err(error) => Err(report_asset_load_error(
+                        &asset_load_errors,
+                        FailedAsset::AnimatedModel {
+                            entity,
+                            url: url.clone(),
+                        },
+                        FailedLoad::AnimatedModel(pending_load),
+                        error.context(format!(
+                            "Got an error while trying to load a model from '{}'",
+                            url
+                        )),
                     )),
                 }
             }
-        });
-    });
+        };
+
+        spawn_abortable(future, abort_registration);
+    }
+}
+
+// `RemovedComponents` can't be used as a query filter, so entities whose scene was removed are
+// added to the changed ones, unless they're already in there.
+fn entities_to_load<F: ReadOnlyWorldQuery>(
+    changed: &Query<Entity, F>,
+    removed_scenes: &HashSet<Entity>,
+) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = changed.iter().collect();
+
+    entities.extend(
+        removed_scenes
+            .iter()
+            .copied()
+            .filter(|&entity| !changed.contains(entity)),
+    );
+
+    entities
 }
 
+// Inserting the new model drops the old one (if the model was reloaded), freeing its geometry.
+// Pending loads can be shared between entities, so they're left filled and the component is
+// removed.
 pub(crate) fn finish_loading_models(
-    static_models: Query<(Entity, &PendingModel)>,
-    animated_models: Query<(Entity, &PendingAnimatedModel)>,
+    static_models: Query<(Entity, &PendingModel, &ModelUrl, Option<&ModelScene>)>,
+    animated_models: Query<(
+        Entity,
+        &PendingAnimatedModel,
+        &AnimatedModelUrl,
+        Option<&ModelScene>,
+        Option<&AnimatedModel>,
+    )>,
+    animated_instances: Query<(Entity, &InstanceOf), With<AnimationJoints>>,
     device: Res<Device>,
     bind_group_layouts: Res<BindGroupLayouts>,
+    empty_morph_weights_buffer: Res<EmptyMorphWeightsBuffer>,
+    mut asset_cache: ResMut<AssetCache>,
     mut commands: Commands,
 ) {
-    static_models.for_each(|(entity, pending_model)| {
-        if let Some(loaded_model) = pending_model.0.swap(None) {
-            commands.entity(entity).insert(Model(loaded_model));
+    static_models.for_each(|(entity, pending_model, url, scene)| {
+        if let Some(loaded_model) = pending_model.0.loaded() {
+            insert_loaded_model(
+                &mut asset_cache,
+                |asset_cache| &mut asset_cache.models,
+                requested_model_key(&url.0, scene),
+                &loaded_model.scene_indices,
+                &loaded_model,
+            );
+
+            commands
+                .entity(entity)
+                .remove::<PendingModel>()
+                .insert(Model(loaded_model))
+                .insert(LoadState::Ready);
         }
     });
 
-    animated_models.for_each(|(entity, pending_model)| {
-        if let Some(loaded_model) = pending_model.0.swap(None) {
+    animated_models.for_each(|(entity, pending_model, url, scene, previous_model)| {
+        if let Some(loaded_model) = pending_model.0.loaded() {
+            insert_loaded_model(
+                &mut asset_cache,
+                |asset_cache| &mut asset_cache.animated_models,
+                requested_model_key(&url.0, scene),
+                &loaded_model.scene_indices,
+                &loaded_model,
+            );
+
             commands
                 .entity(entity)
+                .remove::<PendingAnimatedModel>()
                 .insert(AnimatedModel(loaded_model))
-                .insert(JointBuffers::new(&device.0, &bind_group_layouts.0));
+                .insert(JointBuffers::new(
+                    &device.0,
+                    &bind_group_layouts.0,
+                    &loaded_model.morph_target_texture.view,
+                    (loaded_model.num_morph_weights() == 0)
+                        .then(|| empty_morph_weights_buffer.0.clone()),
+                ))
+                .insert(LoadState::Ready);
+
+            // The joints and morph weights of the instances were cloned from the previous model,
+            // so remove them and let `add_joints_to_instances` re-add them from the new one.
+            if previous_model.is_some() {
+                animated_instances.for_each(|(instance, instance_of)| {
+                    if instance_of.0 == entity {
+                        commands
+                            .entity(instance)
+                            .remove::<(AnimationJoints, MorphWeights)>();
+                    }
+                });
+            }
         }
     })
 }
 
+// Replace the cameras and lights of models with `SpawnSceneObjects` whenever they are (re)loaded
+// or the component is added, and despawn the ones whose model has gone away.
+#[allow(clippy::type_complexity)]
+pub(crate) fn spawn_scene_objects(
+    loaded_models: Query<
+        (Entity, &Model),
+        (
+            Or<(Changed<Model>, Added<SpawnSceneObjects>)>,
+            With<SpawnSceneObjects>,
+        ),
+    >,
+    loaded_animated_models: Query<
+        (Entity, &AnimatedModel),
+        (
+            Or<(Changed<AnimatedModel>, Added<SpawnSceneObjects>)>,
+            With<SpawnSceneObjects>,
+        ),
+    >,
+    models: Query<
+        (),
+        (
+            Or<(With<Model>, With<AnimatedModel>)>,
+            With<SpawnSceneObjects>,
+        ),
+    >,
+    scene_objects: Query<(Entity, &SceneObjectOf)>,
+    mut commands: Commands,
+) {
+    scene_objects.for_each(|(entity, scene_object_of)| {
+        let model = scene_object_of.0;
+
+        if loaded_models.contains(model)
+            || loaded_animated_models.contains(model)
+            || !models.contains(model)
+        {
+            commands.entity(entity).despawn();
+        }
+    });
+
+    loaded_models.for_each(|(entity, model)| {
+        spawn_objects_of_scene(entity, &model.0.scene_objects, &mut commands);
+    });
+
+    loaded_animated_models.for_each(|(entity, model)| {
+        spawn_objects_of_scene(entity, &model.0.scene_objects, &mut commands);
+    });
+}
+
+fn spawn_objects_of_scene(
+    model: Entity,
+    scene_objects: &assets::models::SceneObjects,
+    commands: &mut Commands,
+) {
+    for camera in &scene_objects.cameras {
+        commands.spawn((
+            SceneCamera(camera.clone()),
+            Instance(camera.transform),
+            SceneObjectOf(model),
+        ));
+    }
+
+    for light in &scene_objects.lights {
+        commands.spawn((
+            SceneLight(light.clone()),
+            Instance(light.transform),
+            SceneObjectOf(model),
+        ));
+    }
+}
+
+// Dropping the last reference to a model returns its geometry to the shared buffers, so all we need
+// to do here is remove the model components when the url is removed. Despawned entities drop their
+// components automatically.
+pub(crate) fn unload_models(
+    mut removed_static_models: RemovedComponents<ModelUrl>,
+    mut removed_animated_models: RemovedComponents<AnimatedModelUrl>,
+    mut commands: Commands,
+) {
+    for entity in removed_static_models.iter() {
+        if let Some(mut entity_commands) = commands.get_entity(entity) {
+            entity_commands.remove::<(
+                Model,
+                PendingModel,
+                LoadState,
+                ModelLoadProgress,
+                ModelLoadPriority,
+            )>();
+        }
+    }
+
+    for entity in removed_animated_models.iter() {
+        if let Some(mut entity_commands) = commands.get_entity(entity) {
+            entity_commands.remove::<(
+                AnimatedModel,
+                PendingAnimatedModel,
+                JointBuffers,
+                LoadState,
+                ModelLoadProgress,
+                ModelLoadPriority,
+            )>();
+        }
+    }
+}
+
+// Set the priority of each model's loads from its explicit priority and the distance from the camera
+// to its closest instance. Loads shared between entities get the highest of their priorities.
+pub(crate) fn update_load_priorities(
+    models: Query<(Entity, &ModelLoadPriority, Option<&LoadPriority>)>,
+    instances: Query<(&InstanceOf, &Instance)>,
+    camera: Res<Camera>,
+    load_scheduler: Res<LoadScheduler>,
+) {
+    if load_scheduler.0.num_waiting_loads() == 0 {
+        return;
+    }
+
+    let mut distances = std::collections::HashMap::new();
+
+    instances.for_each(|(instance_of, instance)| {
+        let distance = instance.0.translation().distance(camera.position);
+        let closest = distances.entry(instance_of.0).or_insert(f32::INFINITY);
+        *closest = closest.min(distance);
+    });
+
+    let mut priorities = std::collections::HashMap::new();
+
+    models.for_each(|(entity, model_load_priority, load_priority)| {
+        let explicit = load_priority
+            .map(|load_priority| load_priority.0)
+            .unwrap_or(0);
+        let distance = distances.get(&entity).copied().unwrap_or(f32::INFINITY);
+
+        let (_, highest_explicit, closest_distance) = priorities
+            .entry(Arc::as_ptr(&model_load_priority.0))
+            .or_insert_with(|| (model_load_priority.0.clone(), i32::MIN, f32::INFINITY));
+
+        if explicit > *highest_explicit
+            || (explicit == *highest_explicit && distance < *closest_distance)
+        {
+            *highest_explicit = explicit;
+            *closest_distance = distance;
+        }
+    });
+
+    for (priority, explicit, distance) in priorities.values() {
+        priority.set(*explicit, *distance);
+    }
+}
+
+// Poll the modification times of the files that watched models were loaded from, marking the url
+// as changed so that `start_loading_models` reloads the model. The first time a file is seen is
+// only recorded.
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn watch_model_files(
+    mut watched_models: Query<(
+        &mut WatchModelFile,
+        Option<&mut ModelUrl>,
+        Option<&mut AnimatedModelUrl>,
+        Option<&Model>,
+        Option<&AnimatedModel>,
+    )>,
+    asset_cache: Res<AssetCache>,
+    mut last_checked: Local<Option<std::time::Instant>>,
+) {
+    use bevy_ecs::change_detection::DetectChangesMut;
+
+    let now = std::time::Instant::now();
+
+    if let Some(last_checked) = *last_checked {
+        if now.duration_since(last_checked) < std::time::Duration::from_millis(500) {
+            return;
+        }
+    }
+
+    *last_checked = Some(now);
+
+    for (mut watch, model_url, animated_model_url, model, animated_model) in
+        watched_models.iter_mut()
+    {
+        let url = match (&model_url, &animated_model_url) {
+            (Some(model_url), _) => model_url.0.clone(),
+            (_, Some(animated_model_url)) => animated_model_url.0.clone(),
+            (None, None) => continue,
+        };
+
+        // Until the model has loaded we only know about the gltf itself.
+        let source_urls = match (model, animated_model) {
+            (Some(model), _) => &model.0.source_urls[..],
+            (_, Some(animated_model)) => &animated_model.0.source_urls[..],
+            (None, None) => std::slice::from_ref(&url),
+        };
+
+        let mut any_modified = false;
+
+        for source_url in source_urls {
+            let path = match source_url.to_file_path() {
+                Ok(path) => path,
+                Err(()) => continue,
+            };
+
+            let modified = match std::fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                Ok(modified) => modified,
+                Err(_) => continue,
+            };
+
+            let previously_modified = watch.modified.insert(path, modified);
+
+            if previously_modified.is_some() && previously_modified != Some(modified) {
+                log::info!("{} has been modified", source_url);
+
+                // Stop the texture cache from handing out the old image.
+                asset_cache.textures.remove(source_url);
+
+                any_modified = true;
+            }
+        }
+
+        if !any_modified {
+            continue;
+        }
+
+        log::info!("Reloading {}", url);
+
+        if let Some(mut model_url) = model_url {
+            model_url.set_changed();
+        }
+
+        if let Some(mut animated_model_url) = animated_model_url {
+            animated_model_url.set_changed();
+        }
+    }
+}
+
+pub(crate) fn compact_geometry_buffers(
+    mut compact_geometry_buffers: ResMut<CompactGeometryBuffers>,
+    mut static_models: Query<(&mut Model, &ModelUrl, Option<&ModelScene>)>,
+    mut animated_models: Query<(&mut AnimatedModel, &AnimatedModelUrl, Option<&ModelScene>)>,
+    mut asset_cache: ResMut<AssetCache>,
+    device: Res<Device>,
+    queue: Res<Queue>,
+    (index_buffer, vertex_buffers, animated_vertex_buffers): (
+        Res<IndexBuffer>,
+        Res<VertexBuffers>,
+        Res<AnimatedVertexBuffers>,
+    ),
+) {
+    if !compact_geometry_buffers.requested {
+        return;
+    }
+
+    if asset_cache.models.is_loading() || asset_cache.animated_models.is_loading() {
+        return;
+    }
+
+    compact_geometry_buffers.requested = false;
+
+    asset_cache.models.forget_loaded();
+    asset_cache.animated_models.forget_loaded();
+
+    // We can only patch the ranges of models that we have unique access to. Any other allocations
+    // (models that are shared or still being loaded) stay where they are.
+    let mut movable_indices = Vec::new();
+    let mut movable_vertices = Vec::new();
+    let mut movable_animated_vertices = Vec::new();
+
+    static_models.for_each_mut(|(mut model, ..)| {
+        if let Some(model) = Arc::get_mut(&mut model.0) {
+            movable_indices.push(model.index_buffer_range.clone());
+            movable_vertices.push(model.vertex_buffer_range.clone());
+        }
+    });
+
+    animated_models.for_each_mut(|(mut model, ..)| {
+        if let Some(model) = Arc::get_mut(&mut model.0) {
+            movable_indices.push(model.index_buffer_range.clone());
+            movable_animated_vertices.push(model.vertex_buffer_range.clone());
+        }
+    });
+
+    let shrink_threshold = compact_geometry_buffers.shrink_threshold;
+
+    let mut command_encoder = device
+        .0
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("compaction command encoder"),
+        });
+
+    let index_relocations = index_buffer.0.compact(
+        &movable_indices,
+        shrink_threshold,
+        &device.0,
+        &mut command_encoder,
+    );
+    let vertex_relocations = vertex_buffers.0.compact(
+        &movable_vertices,
+        shrink_threshold,
+        &device.0,
+        &mut command_encoder,
+    );
+    let animated_vertex_relocations = animated_vertex_buffers.0.compact(
+        &movable_animated_vertices,
+        shrink_threshold,
+        &device.0,
+        &mut command_encoder,
+    );
+
+    queue.0.submit(std::iter::once(command_encoder.finish()));
+
+    static_models.for_each_mut(|(mut model, url, scene)| {
+        if let Some(model) = Arc::get_mut(&mut model.0) {
+            model.relocate(&index_relocations, &vertex_relocations);
+        }
+
+        let requested_key = requested_model_key(&url.0, scene);
+        let key = asset_cache.model_key(&requested_key.0, &requested_key.1);
+        asset_cache.models.insert_loaded(&requested_key, &model.0);
+        asset_cache.models.insert_loaded(&key, &model.0);
+    });
+
+    animated_models.for_each_mut(|(mut model, url, scene)| {
+        if let Some(model) = Arc::get_mut(&mut model.0) {
+            model.relocate(&index_relocations, &animated_vertex_relocations);
+        }
+
+        let requested_key = requested_model_key(&url.0, scene);
+        let key = asset_cache.model_key(&requested_key.0, &requested_key.1);
+        asset_cache
+            .animated_models
+            .insert_loaded(&requested_key, &model.0);
+        asset_cache.animated_models.insert_loaded(&key, &model.0);
+    });
+}
+
+// The key for the scene that an entity asked for, before it's resolved to an index.
+fn requested_model_key(url: &url::Url, scene: Option<&ModelScene>) -> ModelKey {
+    (
+        url.clone(),
+        scene.map(|scene| scene.0.clone()).unwrap_or_default(),
+    )
+}
+
+fn model_key(asset_cache: &AssetCache, url: &url::Url, scene: Option<&ModelScene>) -> ModelKey {
+    let (url, scene) = requested_model_key(url, scene);
+    asset_cache.model_key(&url, &scene)
+}
+
+// Loads are cached under the key they were started with, which is only resolved to a scene index
+// if the url has been loaded before. Once the scenes of the url are known, the model is also
+// cached under the index of the scene that it was loaded from so that other names for the same
+// scene share it.
+fn insert_loaded_model<T>(
+    asset_cache: &mut AssetCache,
+    cache: impl Fn(&mut AssetCache) -> &mut ModelCache<T>,
+    requested_key: ModelKey,
+    scene_indices: &assets::models::SceneIndices,
+    model: &Arc<T>,
+) {
+    asset_cache
+        .scene_indices
+        .insert(requested_key.0.clone(), scene_indices.clone());
+
+    let key = asset_cache.model_key(&requested_key.0, &requested_key.1);
+
+    let cache = cache(asset_cache);
+    cache.insert_loaded(&requested_key, model);
+    cache.insert_loaded(&key, model);
+
+    if key != requested_key {
+        cache.insert_alias(&requested_key, key);
+    }
+}
+
+// Store an error so that it can be sent as an event, returning it so that it still gets logged by
+// `spawn`.
+fn report_asset_load_error(
+    asset_load_errors: &parking_lot::Mutex<Vec<(AssetLoadError, FailedLoad)>>,
+    asset: FailedAsset,
+    failed_load: FailedLoad,
+    error: anyhow::Error,
+) -> anyhow::Error {
+    let error = Arc::new(error);
+
+    asset_load_errors.lock().push((
+        AssetLoadError {
+            asset,
+            error: error.clone(),
+        },
+        failed_load,
+    ));
+
+    anyhow::Error::new(assets::http_clients::SharedError(error))
+}
+
+// Mark every entity that is waiting on a failed load as failed. This can include entities other
+// than the one the load was started for, and excludes entities that have moved on to a different
+// load since.
+pub(crate) fn report_asset_load_errors(
+    asset_load_errors: Res<AssetLoadErrors>,
+    pending_models: Query<(Entity, &PendingModel)>,
+    pending_animated_models: Query<(Entity, &PendingAnimatedModel)>,
+    mut event_writer: EventWriter<AssetLoadError>,
+    mut commands: Commands,
+) {
+    let errors = std::mem::take(&mut *asset_load_errors.0.lock());
+
+    for (error, failed_load) in errors {
+        let mut failed_entities = Vec::new();
+
+        match &failed_load {
+            FailedLoad::Model(failed_load) => {
+                pending_models.for_each(|(entity, pending_model)| {
+                    if std::ptr::eq(Arc::as_ptr(&pending_model.0), failed_load.as_ptr()) {
+                        failed_entities.push(entity);
+                    }
+                });
+            }
+            FailedLoad::AnimatedModel(failed_load) => {
+                pending_animated_models.for_each(|(entity, pending_model)| {
+                    if std::ptr::eq(Arc::as_ptr(&pending_model.0), failed_load.as_ptr()) {
+                        failed_entities.push(entity);
+                    }
+                });
+            }
+            FailedLoad::Other => {}
+        }
+
+        for entity in failed_entities {
+            if let Some(mut entity_commands) = commands.get_entity(entity) {
+                entity_commands
+                    .remove::<(PendingModel, PendingAnimatedModel)>()
+                    .insert(LoadState::Failed(error.error.clone()));
+            }
+        }
+
+        event_writer.send(error);
+    }
+}
+
 pub(crate) fn add_joints_to_instances(
     animated_models: Query<&AnimatedModel>,
     instances: Query<(Entity, &InstanceOf), Without<AnimationJoints>>,
@@ -1139,8 +1938,9 @@ pub(crate) fn add_joints_to_instances(
 ) {
     instances.for_each(|(entity, instance_of)| {
         if let Ok(animated_model) = animated_models.get(instance_of.0) {
-            commands.entity(entity).insert(AnimationJoints(
-                animated_model.0.animation_data.animation_joints.clone(),
+            commands.entity(entity).insert((
+                AnimationJoints(animated_model.0.animation_data.animation_joints.clone()),
+                MorphWeights(animated_model.0.animation_data.morph_weights.clone()),
             ));
         }
     })
@@ -49,16 +49,16 @@ pub(crate) fn push_bounding_boxes_to_lines_buffer(
     instance_query.for_each(|(instance_of, instance)| {
         if let Ok(model) = model_query.get(instance_of.0) {
             for (primitive_id, primitive) in model.0.primitives.iter().enumerate() {
-                let vertices =
-                    primitive
-                        .bounding_box
-                        .line_points()
-                        .map(|point| renderer_core::LineVertex {
-                            position: instance.0 * primitive.transform * point,
+                for &transform in &primitive.transforms {
+                    let vertices = primitive.bounding_box.line_points().map(|point| {
+                        renderer_core::LineVertex {
+                            position: instance.0 * transform * point,
                             colour_id: primitive_id as u32,
-                        });
+                        }
+                    });
 
-                line_buffer.staging.extend_from_slice(&vertices);
+                    line_buffer.staging.extend_from_slice(&vertices);
+                }
             }
         }
     })
@@ -2,10 +2,12 @@ use crate::resources::{
     self, AnimatedVertexBuffers, Device, IndexBuffer, InstanceBuffer, LineBuffer, MainBindGroup,
     ParticleBuffer, PipelineOptions, Pipelines, Queue, SurfaceFrameView, VertexBuffers,
 };
+use renderer_core::glam::{UVec4, Vec2, Vec3, Vec4};
 use renderer_core::{
     arc_swap, assets::models::Ranges, instance::ParticleInstance, permutations,
     pipelines::DEPTH_FORMAT, LineVertex, RawAnimatedVertexBuffers, RawVertexBuffers, VecGpuBuffer,
 };
+use std::mem::size_of;
 use std::ops::Range;
 use std::sync::Arc;
 
@@ -15,25 +17,70 @@ use renderer_core::assets::models::PrimitiveRanges;
 #[cfg(feature = "webgl")]
 use renderer_core::create_view_from_device_framebuffer;
 
+// The indices of a model are relative to the start of its vertices, so the vertex buffers are bound
+// from there for each model. WebGL 2 doesn't support a base vertex in draw calls.
 fn bind_static_vertex_buffers<'a>(
     render_pass: &mut wgpu::RenderPass<'a>,
     vertex_buffers: &'a RawVertexBuffers<arc_swap::Guard<Arc<wgpu::Buffer>>>,
+    first_vertex: u32,
 ) {
-    render_pass.set_vertex_buffer(1, vertex_buffers.position.slice(..));
-    render_pass.set_vertex_buffer(2, vertex_buffers.normal.slice(..));
-    render_pass.set_vertex_buffer(3, vertex_buffers.uv.slice(..));
-    render_pass.set_vertex_buffer(4, vertex_buffers.lightmap_uv.slice(..));
+    let offset = |element_size: usize| first_vertex as u64 * element_size as u64;
+
+    render_pass.set_vertex_buffer(
+        1,
+        vertex_buffers.position.slice(offset(size_of::<Vec3>())..),
+    );
+    render_pass.set_vertex_buffer(2, vertex_buffers.normal.slice(offset(size_of::<Vec3>())..));
+    render_pass.set_vertex_buffer(3, vertex_buffers.uv.slice(offset(size_of::<Vec2>())..));
+    render_pass.set_vertex_buffer(
+        4,
+        vertex_buffers
+            .lightmap_uv
+            .slice(offset(size_of::<Vec2>())..),
+    );
+    render_pass.set_vertex_buffer(
+        5,
+        vertex_buffers.colour.slice(offset(size_of::<[u8; 4]>())..),
+    );
 }
 
 fn bind_animated_vertex_buffers<'a>(
     render_pass: &mut wgpu::RenderPass<'a>,
     vertex_buffers: &'a RawAnimatedVertexBuffers<arc_swap::Guard<Arc<wgpu::Buffer>>>,
+    first_vertex: u32,
 ) {
-    render_pass.set_vertex_buffer(1, vertex_buffers.position.slice(..));
-    render_pass.set_vertex_buffer(2, vertex_buffers.normal.slice(..));
-    render_pass.set_vertex_buffer(3, vertex_buffers.uv.slice(..));
-    render_pass.set_vertex_buffer(4, vertex_buffers.joint_indices.slice(..));
-    render_pass.set_vertex_buffer(5, vertex_buffers.joint_weights.slice(..));
+    let offset = |element_size: usize| first_vertex as u64 * element_size as u64;
+
+    render_pass.set_vertex_buffer(
+        1,
+        vertex_buffers.position.slice(offset(size_of::<Vec3>())..),
+    );
+    render_pass.set_vertex_buffer(2, vertex_buffers.normal.slice(offset(size_of::<Vec3>())..));
+    render_pass.set_vertex_buffer(3, vertex_buffers.uv.slice(offset(size_of::<Vec2>())..));
+    render_pass.set_vertex_buffer(
+        4,
+        vertex_slice(
+            &vertex_buffers.joint_indices,
+            first_vertex,
+            size_of::<UVec4>(),
+        ),
+    );
+    render_pass.set_vertex_buffer(
+        5,
+        vertex_slice(
+            &vertex_buffers.joint_weights,
+            first_vertex,
+            size_of::<Vec4>(),
+        ),
+    );
+    render_pass.set_vertex_buffer(
+        6,
+        vertex_buffers.colour.slice(offset(size_of::<[u8; 4]>())..),
+    );
+    render_pass.set_vertex_buffer(
+        7,
+        vertex_buffers.morph_index.slice(offset(size_of::<u32>())..),
+    );
 }
 
 type ModelQuery<'world, 'state, 'component> =
@@ -448,13 +495,12 @@ fn render_mode<'a, R: Fn(&PrimitiveRanges) -> permutations::FaceSides<Ranges>>(
     pipelines: &'a permutations::ModelTypes<permutations::FaceSides<wgpu::RenderPipeline>>,
     range_getter: R,
 ) {
-    bind_static_vertex_buffers(render_pass, context.vertex_buffers);
-
     render_pass.set_pipeline(&pipelines.stationary.single);
 
     render_all_primitives(
         render_pass,
         static_models,
+        context.vertex_buffers,
         context.static_model_bind_groups,
         |primitive_ranges| range_getter(primitive_ranges).single.primitives,
     );
@@ -464,17 +510,17 @@ fn render_mode<'a, R: Fn(&PrimitiveRanges) -> permutations::FaceSides<Ranges>>(
     render_all_primitives(
         render_pass,
         static_models,
+        context.vertex_buffers,
         context.static_model_bind_groups,
         |primitive_ranges| range_getter(primitive_ranges).double.primitives,
     );
 
-    bind_animated_vertex_buffers(render_pass, context.animated_vertex_buffers);
-
     render_pass.set_pipeline(&pipelines.animated.single);
 
     render_all_animated_primitives(
         render_pass,
         animated_models,
+        context.animated_vertex_buffers,
         context.animated_model_bind_groups,
         |primitive_ranges| range_getter(primitive_ranges).single.primitives,
     );
@@ -484,6 +530,7 @@ fn render_mode<'a, R: Fn(&PrimitiveRanges) -> permutations::FaceSides<Ranges>>(
     render_all_animated_primitives(
         render_pass,
         animated_models,
+        context.animated_vertex_buffers,
         context.animated_model_bind_groups,
         |primitive_ranges| range_getter(primitive_ranges).double.primitives,
     );
@@ -611,6 +658,7 @@ impl ModelBindGroups {
 fn render_all_primitives<'a, G: Fn(&PrimitiveRanges) -> Range<usize>>(
     render_pass: &mut wgpu::RenderPass<'a>,
     models: &ModelQuery,
+    vertex_buffers: &'a RawVertexBuffers<arc_swap::Guard<Arc<wgpu::Buffer>>>,
     model_bind_groups: &'a ModelBindGroups,
     primitive_range_getter: G,
 ) {
@@ -618,6 +666,16 @@ fn render_all_primitives<'a, G: Fn(&PrimitiveRanges) -> Range<usize>>(
         // Get the range of primitives we're rendering
         let range = primitive_range_getter(&model.0.primitive_ranges);
 
+        if range.is_empty() {
+            continue;
+        }
+
+        bind_static_vertex_buffers(
+            render_pass,
+            vertex_buffers,
+            model.0.vertex_buffer_range.start,
+        );
+
         // Get the primitives we're rendering
         let primitives = &model.0.primitives[range.clone()];
 
@@ -625,25 +683,24 @@ fn render_all_primitives<'a, G: Fn(&PrimitiveRanges) -> Range<usize>>(
         // if a particular LOD level isn't drawn, we don't have to do the inner
         // primitives loop.
         for (lod_index, lod) in instance_ranges.lods.iter().enumerate() {
-            let instance_ranges = &lod.ranges[range.clone()];
-
-            for (primitive, instance_range) in primitives.iter().zip(instance_ranges) {
-                if instance_range.is_empty() {
-                    continue;
-                }
+            let run_ranges = &lod.ranges[range.clone()];
 
+            for (primitive, run_range) in primitives.iter().zip(run_ranges) {
                 let primitive = &primitive.lods[lod_index];
 
-                let bind_group =
-                    &model_bind_groups.get_bind_group(model_index, primitive.material_index);
+                // Instances are grouped by material, so draw each group with its own bind group.
+                for run in &lod.runs[run_range.clone()] {
+                    let bind_group =
+                        &model_bind_groups.get_bind_group(model_index, run.material_index);
 
-                render_pass.set_bind_group(1, bind_group, &[]);
+                    render_pass.set_bind_group(1, bind_group, &[]);
 
-                render_pass.draw_indexed(
-                    primitive.index_buffer_range.clone(),
-                    0,
-                    instance_range.clone(),
-                );
+                    render_pass.draw_indexed(
+                        primitive.index_buffer_range.clone(),
+                        0,
+                        run.instances.clone(),
+                    );
+                }
             }
         }
     }
@@ -652,6 +709,7 @@ fn render_all_primitives<'a, G: Fn(&PrimitiveRanges) -> Range<usize>>(
 fn render_all_animated_primitives<'a, G: Fn(&PrimitiveRanges) -> Range<usize>>(
     render_pass: &mut wgpu::RenderPass<'a>,
     models: &'a AnimatedModelQuery,
+    vertex_buffers: &'a RawAnimatedVertexBuffers<arc_swap::Guard<Arc<wgpu::Buffer>>>,
     model_bind_groups: &'a ModelBindGroups,
     primitive_range_getter: G,
 ) {
@@ -659,44 +717,64 @@ fn render_all_animated_primitives<'a, G: Fn(&PrimitiveRanges) -> Range<usize>>(
         // Get the range of primitives we're rendering
         let range = primitive_range_getter(&model.0.primitive_ranges);
 
+        if range.is_empty() {
+            continue;
+        }
+
+        bind_animated_vertex_buffers(
+            render_pass,
+            vertex_buffers,
+            model.0.vertex_buffer_range.start,
+        );
+
         // Get the primitives we're rendering
         let primitives = &model.0.primitives[range.clone()];
 
         for (lod_index, lod) in instance_ranges.lods.iter().enumerate() {
-            let instance_ranges = &lod.ranges[range.clone()];
+            let run_ranges = &lod.ranges[range.clone()];
 
-            for (primitive, instance_range) in primitives.iter().zip(instance_ranges) {
-                if instance_range.is_empty() {
-                    continue;
-                }
+            for (primitive, run_range) in primitives.iter().zip(run_ranges) {
+                let runs = &lod.runs[run_range.clone()];
+
+                let primitive_start = match runs.first() {
+                    Some(run) => run.instances.start,
+                    None => continue,
+                };
 
                 let primitive = &primitive.lods[lod_index];
 
-                let bind_group =
-                    model_bind_groups.get_bind_group(model_index, primitive.material_index);
+                let max_instances = model.0.max_instances_per_joint_buffer();
 
-                render_pass.set_bind_group(1, bind_group, &[]);
+                for run in runs {
+                    let bind_group =
+                        model_bind_groups.get_bind_group(model_index, run.material_index);
 
-                let mut joint_buffer_index = 0;
-                let mut instance_offset = instance_range.start;
+                    render_pass.set_bind_group(1, bind_group, &[]);
 
-                // todo: Remove this ASAP when we can switch to WebGPU.
-                while instance_offset < instance_range.end {
-                    let end = (instance_offset + model.0.max_instances_per_joint_buffer())
-                        .min(instance_range.end);
+                    let mut instance_offset = run.instances.start;
 
-                    if let Some(joint_buffer) = joint_buffers.buffers.get(joint_buffer_index) {
-                        render_pass.set_bind_group(2, &joint_buffer.bind_group, &[]);
+                    // todo: Remove this ASAP when we can switch to WebGPU.
+                    while instance_offset < run.instances.end {
+                        // Joint buffers are split relative to the first instance of the primitive.
+                        let joint_buffer_index =
+                            ((instance_offset - primitive_start) / max_instances) as usize;
 
-                        render_pass.draw_indexed(
-                            primitive.index_buffer_range.clone(),
-                            0,
-                            instance_offset..end,
-                        );
-                    }
+                        let end = (primitive_start
+                            + (joint_buffer_index as u32 + 1) * max_instances)
+                            .min(run.instances.end);
 
-                    instance_offset = end;
-                    joint_buffer_index += 1;
+                        if let Some(joint_buffer) = joint_buffers.buffers.get(joint_buffer_index) {
+                            render_pass.set_bind_group(2, &joint_buffer.bind_group, &[]);
+
+                            render_pass.draw_indexed(
+                                primitive.index_buffer_range.clone(),
+                                0,
+                                instance_offset..end,
+                            );
+                        }
+
+                        instance_offset = end;
+                    }
                 }
             }
         }